@@ -8,7 +8,7 @@ use legion::systems::Builder;
 use legion::storage::ComponentTypeId;
 
 use engine::alarm;
-use alarm::{Cone, Observer, Suspicious, Walls};
+use alarm::{Cone, Cover, Disturbance, Hidden, Observers, Suspicious, Transmissions, Tripwire, Walls};
 
 use engine::camera;
 use camera::{Camera, Target};
@@ -16,26 +16,31 @@ use camera::{Camera, Target};
 use engine::codes;
 use codes::{Codes};
 
-use engine::game::{State, Timestep};
+use engine::game::{State, TimeScale, Timestep};
 
 use engine::draw::{Draw, Stroke};
 
 use engine::input::Input;
 
 use engine::physics;
-use physics::{DynamicBody, Gravity, HasGravity, InteractsWithOneWay, Kinematic, OneWayBody, StaticBody, Velocity};
+use physics::{Broadphase, Climbable, ContactEvents, Contacts, DynamicBody, Fluid, Gravity, GravityZone, HasGravity, InteractsWithOneWay, Kinematic, OneWayBody, PolygonBody, StaticBody, Velocity};
+
+use engine::rope;
+use rope::Rope;
 
 use engine::sprites;
 use sprites::{Sheets, SpriteSheet};
 
-use engine::space::{Point, Rect};
+use engine::space::Rect;
+
+use game::guard;
 
 use game::player;
-use game::player::{Player, InputCommand};
+use game::player::InputCommand;
 
 use game::level;
 
-use game::eventmap::Trigger;
+use game::eventmap::{HideZone, Terminal, Trigger};
 
 pub struct Snoop
 {
@@ -44,7 +49,8 @@ pub struct Snoop
     schedule: Schedule,
     resources: Resources,
     view: SfBox<View>,
-    debug: bool
+    debug: bool,
+    time_keys: Vec<bool> //Debug time controls held last frame, so each press only acts once
 
 }
 
@@ -63,39 +69,36 @@ impl Snoop
 
         let mut resources = Resources::default();
         resources.insert(Timestep { step });
+        resources.insert(TimeScale::new());
         resources.insert(Gravity { force: 20.0 * timestep, max: 1000.0 * timestep});
 		resources.insert(Walls::new());
         resources.insert(Codes::new());
+        resources.insert(Observers::new());
+        resources.insert(Transmissions::new());
+        resources.insert(Broadphase::new());
+        resources.insert(ContactEvents::new());
         resources.insert(InputCommand::new());
 
         camera::register_camera_resources(&mut resources, 400.0, 400.0);
 
-        world.push(
+        let player = world.push(
         (
 
             SpriteSheet::from_files(&mut draw, &mut sheets, "Character", "./assets/images/", "./assets/data/atlases/", "Character", "./assets/data/sheets/"),
-            Player::new(200.0, 8.0, 50.0),
+            player::load_player("player", "./assets/data/player/"),
             HasGravity {},
             InteractsWithOneWay {},
             Velocity::new(0.0, 0.0),
             DynamicBody::new(50.0, 50.0, 15.0, 50.0),
             Target {},
-            Suspicious { code: 0 }
+            Suspicious::new(0)
 
         ));
 
-        {
-
-        let mut codes = resources.get_mut::<Codes>().unwrap();           
-		world.push(
-		(
-
-			Observer::new(Point { x: 600.0, y: 200.0 }, Point { x: 0.0, y: 0.0 }, Point { x: -3.0, y: 1.0 }, Point { x: -1.0, y: 1.0 } , codes.get_code("camera")),
-			Cone { field: Vec::new() }
-
-		));
-
-        }
+        let mut entry = world.entry(player).unwrap();
+        entry.add_component(player::Player::solid());
+        entry.add_component(player::Player::layers());
+        entry.add_component(Contacts::new());
 
         level::load_level(&mut world, &mut resources, "test", "./assets/data/levels/");
 
@@ -111,7 +114,7 @@ impl Snoop
 
         let schedule = schedule_builder.build();
 
-        return Snoop { world, schedule, resources, view: View::new(Vector2f::new(0.0, 0.0), Vector2f::new(400.0, 400.0)), debug: true };
+        return Snoop { world, schedule, resources, view: View::new(Vector2f::new(0.0, 0.0), Vector2f::new(400.0, 400.0)), debug: true, time_keys: vec![false; 4] };
 
     }
 
@@ -119,6 +122,7 @@ impl Snoop
     {
 
 		player::schedule_early_systems(schedule);
+		guard::schedule_guard_systems(schedule);
 
 		physics::schedule_early_systems(schedule);
 
@@ -127,7 +131,10 @@ impl Snoop
     fn schedule_physics_systems(schedule: &mut Builder)
     {
 
-		physics::schedule_physics_systems(schedule);
+		physics::schedule_force_systems(schedule);
+		rope::schedule_rope_systems(schedule);
+		physics::schedule_movement_systems(schedule);
+		rope::schedule_grip_systems(schedule);
 		
 		alarm::schedule_alarm_systems(schedule);
         codes::schedule_watcher_systems(schedule);
@@ -172,6 +179,17 @@ impl Snoop
 
         }
 
+        //Draw polygon walls
+        let mut polygon_query = <&PolygonBody>::query();
+        for body in polygon_query.iter(&self.world)
+        {
+
+            let polygon = draw.create_polygon(&Stroke::new(outline, fill, 1.0), &body.points);
+
+            window.draw(&polygon);
+
+        }
+
         //Draw oneway walls
         let fill = Color::rgba(0, 255, 0, 100);
         let mut oneway_query = <&OneWayBody>::query().filter(!component::<Kinematic>());
@@ -245,6 +263,83 @@ impl Snoop
 
         }
 
+        //Draw cover
+        let mut cover_query = <&Cover>::query();
+        for cover in cover_query.iter(&mut self.world)
+        {
+
+            let fill = Color::rgba(128, 128, 0, 100);
+            let rect = draw.create_rect(&Stroke::new(outline, fill, 1.0), &cover.rect);
+            window.draw(&rect);
+
+        }
+
+        //Draw ladders
+        let mut ladder_query = <&Climbable>::query();
+        for ladder in ladder_query.iter(&self.world)
+        {
+
+            let fill = Color::rgba(160, 96, 32, 100);
+            let rect = draw.create_rect(&Stroke::new(outline, fill, 1.0), &ladder.rect);
+            window.draw(&rect);
+
+        }
+
+        //Draw fluids
+        let mut fluid_query = <&Fluid>::query();
+        for fluid in fluid_query.iter(&self.world)
+        {
+
+            let fill = Color::rgba(0, 96, 255, 80);
+            let rect = draw.create_rect(&Stroke::new(outline, fill, 1.0), &fluid.rect);
+            window.draw(&rect);
+
+        }
+
+        //Draw gravity zones, flipped ones in a stronger colour
+        let mut zone_query = <&GravityZone>::query();
+        for zone in zone_query.iter(&self.world)
+        {
+
+            let fill = if zone.flip { Color::rgba(160, 0, 255, 60) } else { Color::rgba(96, 64, 160, 40) };
+            let rect = draw.create_rect(&Stroke::new(outline, fill, 1.0), &zone.rect);
+            window.draw(&rect);
+
+        }
+
+        //Draw hide zones
+        let mut hide_query = <&HideZone>::query();
+        for zone in hide_query.iter(&mut self.world)
+        {
+
+            let fill = Color::rgba(64, 64, 64, 100);
+            let rect = draw.create_rect(&Stroke::new(outline, fill, 1.0), &zone.rect);
+            window.draw(&rect);
+
+        }
+
+        //Draw disturbances which have not been noticed yet. Downed guards are already drawn with the other bodies
+        let mut disturbance_query = <&Disturbance>::query().filter(!component::<DynamicBody>());
+        for disturbance in disturbance_query.iter(&mut self.world).filter(|d| { return d.active(); })
+        {
+
+            let fill = Color::rgba(255, 128, 0, 100);
+            let rect = draw.create_rect(&Stroke::new(outline, fill, 1.0), &disturbance.rect);
+            window.draw(&rect);
+
+        }
+
+        //Draw terminals
+        let mut terminal_query = <&Terminal>::query();
+        for terminal in terminal_query.iter(&mut self.world)
+        {
+
+            let fill = Color::rgba(0, 128, 255, 100);
+            let rect = draw.create_rect(&Stroke::new(outline, fill, 1.0), &terminal.rect);
+            window.draw(&rect);
+
+        }
+
     }
 
 }
@@ -288,12 +383,57 @@ impl State for Snoop
         self.resources.remove::<InputCommand>();
         self.resources.insert(command);
 
+        //P pauses, period steps a single frame, and the brackets slow down or speed up time
+        let keys = vec![input.contains(Key::P), input.contains(Key::PERIOD), input.contains(Key::LBRACKET), input.contains(Key::RBRACKET)];
+        let pressed: Vec<bool> = keys.iter().zip(self.time_keys.iter()).map(|(down, old)| { return *down && !*old; }).collect();
+
+        self.time_keys = keys;
+
+        let mut time = self.resources.get_mut::<TimeScale>().unwrap();
+
+        if pressed[0]
+        {
+
+            time.toggle_pause();
+
+        }
+
+        if pressed[1]
+        {
+
+            time.step_once();
+
+        }
+
+        if pressed[2]
+        {
+
+            let scale = time.scale * 0.5;
+            time.set_scale(scale);
+
+        }
+
+        if pressed[3]
+        {
+
+            let scale = time.scale * 2.0;
+            time.set_scale(scale);
+
+        }
+
     }
 
     fn update(&mut self, _timestep: i32) -> bool
     {
 
-        self.schedule.execute(&mut self.world, &mut self.resources);
+        let steps = self.resources.get_mut::<TimeScale>().unwrap().advance();
+
+        for _ in 0..steps
+        {
+
+            self.schedule.execute(&mut self.world, &mut self.resources);
+
+        }
 
         return true
 
@@ -302,6 +442,9 @@ impl State for Snoop
     fn render(&mut self, window: &mut RenderWindow, time: f32)
     {
 
+        //Interpolate by how far the world is towards its next step rather than real time
+        let time = self.resources.get::<TimeScale>().unwrap().blend(time);
+
         //Normal render block, subscoped so that immutable borrows do not interfere with
         //mutable borrow required by debug render block
         {
@@ -312,7 +455,7 @@ impl State for Snoop
             let draw = self.resources.get::<Draw>().unwrap();
             let sheets = self.resources.get::<Sheets>().unwrap();
 
-            let mut sprite_query = <(&SpriteSheet, &DynamicBody, &Velocity)>::query().filter(component::<Target>() | !component::<Target>());
+            let mut sprite_query = <(&SpriteSheet, &DynamicBody, &Velocity)>::query().filter((component::<Target>() | !component::<Target>()) & !component::<Hidden>());
             for chunk in sprite_query.iter_chunks(&self.world)
             {
 
@@ -388,6 +531,38 @@ impl State for Snoop
 
 			}
 
+			let beam_stroke = Stroke::new(Color::rgba(255, 0, 0, 0), Color::rgba(255, 0, 0, 200), 2.0);
+			let mut tripwire_query = <&Tripwire>::query();
+			for tripwire in tripwire_query.iter(&self.world)
+			{
+
+				for segment in tripwire.segments.iter()
+				{
+
+					let line = draw.create_line(&beam_stroke, segment);
+
+					window.draw(&line);
+
+				}
+
+			}
+
+			let rope_stroke = Stroke::new(Color::rgba(0, 0, 0, 0), Color::rgba(170, 120, 60, 255), 2.0);
+			let mut rope_query = <&Rope>::query();
+			for rope in rope_query.iter(&self.world)
+			{
+
+				for segment in rope.segments().iter()
+				{
+
+					let line = draw.create_line(&rope_stroke, segment);
+
+					window.draw(&line);
+
+				}
+
+			}
+
             window.set_view(&self.view);
 
         }
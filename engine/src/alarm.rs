@@ -1,10 +1,15 @@
+use serde::Deserialize;
+
 use legion::*;
 use legion::world::SubWorld;
-use legion::systems::Builder;
+use legion::systems::{Builder, CommandBuffer};
+
+use std::collections::HashMap;
 
 use super::codes::Codes;
-use super::space::{Point, Segment, Triangle};
-use super::physics::{DynamicBody, StaticBody, OneWayBody};
+use super::game::Timestep;
+use super::space::{Point, Rect, Segment, Triangle, FLOATING_POINT_ERROR};
+use super::physics::{Contacts, DynamicBody, StaticBody, OneWayBody, PolygonBody, Submerged, Velocity};
 
 pub struct Observer
 {
@@ -13,207 +18,1579 @@ pub struct Observer
 	offset: Point,
 	upper: Point,
 	lower: Point,
-    code: u128
+    code: u128,
+	left: bool,
+	reaction: i32, //How long a fully visible body must stay in view before it is noticed
+	exposure: f32,
+	watching: Option<Entity>, //Body the exposure was last built up on
+	pub sighting: Option<Point>, //Centre of the suspicious body seen during the last step, if any
+	pub priority: i32, //Priority of whatever was last sighted
+	pub report: Option<Point> //Last known position received over the radio, kept until something acts on it
 
 }
 
-pub struct Cone
+#[derive(Deserialize)]
+pub struct ObserverData
 {
 
-	pub field: Vec<Triangle>
+	pub name: Option<String>,
+	pub offset: Point,
+	pub upper: Point,
+	pub lower: Point,
+	pub code: String,
+	pub reaction: i32,
+	pub radio: Option<RadioData>
 
 }
 
-pub struct Suspicious
+//Lets level data refer to observers by name rather than by the code they raise
+pub struct Observers
 {
 
-    pub code: u128
+	listing: HashMap<String, Entity>
 
 }
 
-impl Observer
+impl Observers
 {
 
-	pub fn new(location: Point, offset: Point, upper: Point, lower: Point, code: u128) -> Observer
+	pub fn new() -> Observers
 	{
 
-		return Observer { location: location + offset, offset: offset, upper: upper, lower: lower, code: code };
+		return Observers { listing: HashMap::new() };
 
 	}
 
-	pub fn shift(&mut self, location: Point)
+	pub fn insert(&mut self, name: &str, entity: Entity)
 	{
 
-		self.location = location + self.offset;
+		self.listing.insert(name.to_string(), entity);
+
+	}
+
+	pub fn get(&self, name: &str) -> Option<Entity>
+	{
+
+		return self.listing.get(name).copied();
 
 	}
 
 }
 
-pub struct Walls
+impl Default for Observers
 {
 
-	segments: Vec<Segment>
+	fn default() -> Observers
+	{
+
+		return Observers::new();
+
+	}
 
 }
 
-impl Walls
+//Switches an observer off for a while, for example after its terminal has been hacked
+pub struct Disabled
 {
 
-	pub fn new() -> Walls
+	remaining: i32
+
+}
+
+impl Disabled
+{
+
+	pub fn new(duration: i32) -> Disabled
 	{
 
-		return Walls { segments: Vec::new() };
+		return Disabled { remaining: duration };
 
 	}
 
 }
-#[system]
-#[read_component(StaticBody)]
-#[read_component(OneWayBody)]
-fn update_wall_segments(world: &mut SubWorld, #[resource] walls: &mut Walls)
+
+//How often an observer which keeps seeing something repeats its broadcast, in milliseconds
+const BROADCAST_INTERVAL: i32 = 500;
+
+//Lets an observer pass sightings on to other observers, either those sharing its network or those within range
+pub struct Radio
 {
 
-	walls.segments.clear();
+	network: Option<u128>,
+	range: f32,
+	delay: i32, //How long a broadcast takes to reach its receivers
+	cooldown: i32
 
-	let mut static_query = <&StaticBody>::query();
+}
 
-	for body in static_query.iter(world)
+#[derive(Deserialize)]
+pub struct RadioData
+{
+
+	pub network: Option<String>,
+	pub range: f32,
+	pub delay: i32
+
+}
+
+impl Radio
+{
+
+	pub fn new(data: &RadioData, codes: &mut Codes) -> Radio
 	{
 
-		let rect = &body.body;
+		let network = data.network.as_ref().map(|name| { return codes.get_code(name); });
 
-		walls.segments.push(Segment::new(Point { x: rect.x, y: rect.y }, Point { x: rect.right(), y: rect.y }));
-		walls.segments.push(Segment::new(Point { x: rect.right(), y: rect.y }, Point { x: rect.right(), y: rect.bottom() }));
-		walls.segments.push(Segment::new(Point { x: rect.right(), y: rect.bottom() }, Point { x: rect.x, y: rect.bottom() }));
-		walls.segments.push(Segment::new(Point { x: rect.x, y: rect.bottom() }, Point { x: rect.x, y: rect.y }));
+		return Radio { network, range: data.range, delay: data.delay, cooldown: 0 };
 
 	}
 
-	let mut oneway_query = <&OneWayBody>::query();
+	fn reaches(&self, from: Point, other: &Radio, to: Point) -> bool
+	{
 
-	for body in oneway_query.iter(world)
+		if self.network.is_some() && self.network == other.network
+		{
+
+			return true;
+
+		}
+
+		let offset = to - from;
+
+		return offset.dot(offset) <= self.range * self.range;
+
+	}
+
+}
+
+struct Transmission
+{
+
+	receiver: Entity,
+	position: Point,
+	remaining: i32
+
+}
+
+//Broadcasts which are still on their way to their receivers
+pub struct Transmissions
+{
+
+	pending: Vec<Transmission>
+
+}
+
+impl Transmissions
+{
+
+	pub fn new() -> Transmissions
 	{
 
-		let rect = &body.body;
+		return Transmissions { pending: Vec::new() };
 
-		walls.segments.push(Segment::new(Point { x: rect.x, y: rect.y }, Point { x: rect.right(), y: rect.y }));
-		walls.segments.push(Segment::new(Point { x: rect.right(), y: rect.y }, Point { x: rect.right(), y: rect.bottom() }));
-		walls.segments.push(Segment::new(Point { x: rect.right(), y: rect.bottom() }, Point { x: rect.x, y: rect.bottom() }));
-		walls.segments.push(Segment::new(Point { x: rect.x, y: rect.bottom() }, Point { x: rect.x, y: rect.y }));
+	}
+
+}
+
+impl Default for Transmissions
+{
+
+	fn default() -> Transmissions
+	{
+
+		return Transmissions::new();
 
 	}
 
 }
 
-#[system(for_each)]
-fn line_of_sight(observer: &mut Observer, cone: &mut Cone, #[resource] walls: &Walls)
+//Beams which raise a code when a suspicious body crosses them. Beams stop at the first wall they hit, and can be
+//switched on and off on a schedule or disabled entirely while a code is active
+pub struct Tripwire
 {
 
-	let mut rays: Vec<Point> = Vec::new();
-	rays.push(observer.lower);
-	rays.push(observer.upper);
+	beams: Vec<Segment>,
+	pub segments: Vec<Segment>, //The beams as currently blocked by walls, empty while the tripwire is off
+	code: u128,
+	disable: Option<u128>,
+	on: i32,
+	off: i32, //Zero keeps the tripwire on permanently
+	time: i32
 
-	let bound_check = |ray: Point| -> bool
+}
+
+impl Tripwire
+{
+
+	pub fn new(beams: Vec<Segment>, code: u128, disable: Option<u128>, on: i32, off: i32, offset: i32) -> Tripwire
 	{
 
-		return ray.ray_between(&observer.lower, &observer.upper);
-		
-	};
+		return Tripwire { beams, segments: Vec::new(), code, disable, on, off, time: offset };
 
-	//Collect the rays we need to project
-	for segment in walls.segments.iter()
+	}
+
+	fn scheduled(&self) -> bool
 	{
 
-		//We only cast a ray to the start of the segment because each corner of each box will be the start of one of the segments			
-		let ray = Point { x: segment.start.x - observer.location.x, y: segment.start.y - observer.location.y };
+		return self.off <= 0 || self.time % (self.on + self.off) < self.on;
 
-		if bound_check(ray)
+	}
+
+}
+
+pub struct Cone
+{
+
+	pub field: Vec<Triangle>
+
+}
+
+//Priority of intruders themselves. Anything lower is only a sign that something is amiss
+pub const INTRUDER_PRIORITY: i32 = 100;
+
+pub struct Suspicious
+{
+
+    pub code: u128,
+	pub visibility: f32, //Scales how quickly observers notice the body, zero makes it invisible
+	pub priority: i32 //Observers watch the highest priority body in view
+
+}
+
+impl Suspicious
+{
+
+	pub fn new(code: u128) -> Suspicious
+	{
+
+		return Suspicious { code, visibility: 1.0, priority: INTRUDER_PRIORITY };
+
+	}
+
+	pub fn with_priority(code: u128, priority: i32) -> Suspicious
+	{
+
+		return Suspicious { code, visibility: 1.0, priority };
+
+	}
+
+}
+
+//Changes to the environment, such as an open door or a light switched off, which become suspicious once their trigger
+//code is raised. Each is only noticed once, after which it is old news
+pub struct Disturbance
+{
+
+	pub rect: Rect,
+	trigger: Option<u128>, //Disturbances without a trigger are bodies which are suspicious while they are disabled
+	active: bool,
+	noticed: bool
+
+}
+
+impl Disturbance
+{
+
+	pub fn new(rect: Rect, trigger: u128) -> Disturbance
+	{
+
+		return Disturbance { rect, trigger: Some(trigger), active: false, noticed: false };
+
+	}
+
+	//For observers with a body, such as guards, which look out of place to other observers while they are knocked out of
+	//action. The body is seen where it lies rather than at a fixed rect
+	pub fn downed() -> Disturbance
+	{
+
+		return Disturbance { rect: Rect { x: 0.0, y: 0.0, width: 0.0, height: 0.0 }, trigger: None, active: false, noticed: false };
+
+	}
+
+	pub fn active(&self) -> bool
+	{
+
+		return self.active && !self.noticed;
+
+	}
+
+}
+
+//Bodies which make a noise when they land hard, such as a crate knocked off a ledge. Observers with a body close enough
+//hear where it landed, whether or not they can see it
+pub struct Noisy
+{
+
+	code: u128,
+	priority: i32,
+	speed: f32, //Slowest landing which can be heard, in pixels per step
+	radius: f32,
+	falling: f32, //Speed towards the ground during the last step
+	grounded: bool
+
+}
+
+#[derive(Deserialize)]
+pub struct NoiseData
+{
+
+	pub code: String,
+	pub priority: i32,
+	pub speed: f32,
+	pub radius: f32
+
+}
+
+impl Noisy
+{
+
+	pub fn new(data: &NoiseData, codes: &mut Codes) -> Noisy
+	{
+
+		return Noisy { code: codes.get_code(&data.code), priority: data.priority, speed: data.speed, radius: data.radius, falling: 0.0, grounded: true };
+
+	}
+
+}
+
+//Marks bodies keeping low enough to be concealed by cover
+pub struct LowProfile {}
+
+//Low walls and similar, which conceal low profile bodies keeping below their top from anything looking at them through
+//the side of the cover. Observers above the cover or looking down over it steeply enough still see them
+pub struct Cover
+{
+
+	pub rect: Rect
+
+}
+
+impl Cover
+{
+
+	fn conceals(&self, body: &Rect, eye: Point) -> bool
+	{
+
+		if !Rect::intersects(body, &self.rect) || body.y < self.rect.y || (eye.x >= self.rect.x && eye.x <= self.rect.right())
 		{
 
-			rays.push(ray);
+			return false;
 
 		}
 
+		let top = Point { x: body.x + body.width * 0.5, y: body.y };
+		let side = if eye.x < self.rect.x { self.rect.x } else { self.rect.right() };
+
+		//How far along the line of sight to the top of the body it passes the side facing the observer
+		let along = (side - eye.x) / (top.x - eye.x);
+
+		return along <= 1.0 && eye.y + (top.y - eye.y) * along >= self.rect.y;
+
 	}
 
-	//Sort the rays from lower to upper
-	Point::sort_from_angle(&mut rays, observer.lower);
-	
-	//Actually create the triangles
-	cone.field.clear();
+}
 
-	for i in 0..rays.len()-1
+//How long a body which has just hidden can still be spotted by observers which were not already watching it. Observers
+//which were keep watching until they notice it or lose interest, so that hiding in plain view does not work
+const HIDE_EXPOSURE: i32 = 250;
+
+pub struct Hidden
+{
+
+	time: i32
+
+}
+
+impl Hidden
+{
+
+	pub fn new() -> Hidden
 	{
 
-		let mut shortest_current = 0.0;
-		let mut shortest_next = 0.0;
+		return Hidden { time: 0 };
 
-		for segment in walls.segments.iter()
-		{
+	}
 
-			let cast_current = segment.raycast(observer.location, rays[i]);
-			let cast_next = segment.raycast(observer.location, rays[i + 1]);
+	fn concealed(&self) -> bool
+	{
 
-			if cast_current.is_some() && cast_next.is_some() && (shortest_current == 0.0 || cast_current.unwrap() < shortest_current)
-			{
+		return self.time >= HIDE_EXPOSURE;
 
-				shortest_current = cast_current.unwrap();
-				shortest_next = cast_next.unwrap();
+	}
 
-			}
+}
 
-		}
+impl Default for Hidden
+{
 
-		cone.field.push(Triangle::new(observer.location, observer.location + rays[i].scale(shortest_current), observer.location + rays[i + 1].scale(shortest_next)));
+	fn default() -> Hidden
+	{
+
+		return Hidden::new();
 
 	}
 
 }
 
-#[system(for_each)]
-#[read_component(DynamicBody)]
-#[read_component(Suspicious)]
-fn visual_alarm(observer: &Observer, cone: &Cone, world: &mut SubWorld, #[resource] codes: &mut Codes)
+impl Observer
 {
 
-    if !codes.contains(observer.code)
-    {
+	pub fn new(location: Point, offset: Point, upper: Point, lower: Point, code: u128, reaction: i32) -> Observer
+	{
+
+		return Observer { location: location + offset, offset: offset, upper: upper, lower: lower, code: code, left: false, reaction, exposure: 0.0, watching: None, sighting: None, priority: 0, report: None };
+
+	}
+
+	pub fn location(&self) -> Point
+	{
+
+		return self.location;
+
+	}
 
-	    let mut query = <(&DynamicBody, &Suspicious)>::query();
+	pub fn shift(&mut self, location: Point)
+	{
 
-	    for (body, suspicious) in query.iter(world)
-	    {
+		self.location = location + self.offset;
 
-            if Codes::codes_interact(suspicious.code, observer.code)
-            {
+	}
 
-		        for triangle in cone.field.iter()
-		        {
+	//Mirror the field of view horizontally. Mirroring reverses orientation, so upper and lower swap
+	pub fn face(&mut self, left: bool)
+	{
 
-			        if triangle.intersects_rectangle(&body.body)
-			        {
+		if self.left != left
+		{
 
-                        codes.insert(observer.code);
-                        break;
+			let upper = Point { x: -self.lower.x, y: self.lower.y };
+			let lower = Point { x: -self.upper.x, y: self.upper.y };
 
-			        }						
+			self.upper = upper;
+			self.lower = lower;
 
-                }
-            
-		    }
+			self.left = left;
 
-	    }
+		}
 
-    }
+	}
 
 }
 
-pub fn schedule_alarm_systems(schedule: &mut Builder)
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AlarmPhase
 {
 
-	schedule.add_system(update_wall_segments_system());
-	schedule.add_system(line_of_sight_system());
+	Calm,
+	Caution,
+	Alert,
+	Lockdown
+
+}
+
+const PHASES: [AlarmPhase; 4] = [AlarmPhase::Calm, AlarmPhase::Caution, AlarmPhase::Alert, AlarmPhase::Lockdown];
+
+#[derive(Default, Deserialize)]
+pub struct PhaseData
+{
+
+	duration: i32, //How long without any detection before the phase decays to the one below
+	escalate: i32, //How much detection time raises the phase to the one above, or zero to never escalate
+	codes: Vec<String>
+
+}
+
+#[derive(Deserialize)]
+pub struct AlarmTriggerData
+{
+
+	code: String,
+	phase: AlarmPhase
+
+}
+
+//Levels without an alarm get one with no triggers, which stays calm
+#[derive(Default, Deserialize)]
+pub struct AlarmData
+{
+
+	triggers: Vec<AlarmTriggerData>,
+	calm: PhaseData,
+	caution: PhaseData,
+	alert: PhaseData,
+	lockdown: PhaseData
+
+}
+
+struct Phase
+{
+
+	duration: i32,
+	escalate: i32,
+	codes: Vec<u128>
+
+}
+
+pub struct AlarmController
+{
+
+	phase: AlarmPhase,
+	quiet: i32,
+	pressure: i32,
+	triggers: Vec<(u128, AlarmPhase)>,
+	raised: Vec<u128>, //Trigger codes read last step, cleared at the start of the next
+	phases: Vec<Phase>
+
+}
+
+impl AlarmController
+{
+
+	pub fn new(data: AlarmData, codes: &mut Codes) -> AlarmController
+	{
+
+		let triggers = data.triggers.iter().map(|t| { return (codes.get_code(&t.code), t.phase); }).collect();
+
+		let mut phases = Vec::new();
+
+		for phase in [data.calm, data.caution, data.alert, data.lockdown].iter()
+		{
+
+			phases.push(Phase { duration: phase.duration, escalate: phase.escalate, codes: phase.codes.iter().map(|c| { return codes.get_code(c); }).collect() });
+
+		}
+
+		let mut controller = AlarmController { phase: AlarmPhase::Calm, quiet: 0, pressure: 0, triggers, raised: Vec::new(), phases };
+		controller.enter(AlarmPhase::Calm, codes);
+
+		return controller;
+
+	}
+
+	pub fn phase(&self) -> AlarmPhase
+	{
+
+		return self.phase;
+
+	}
+
+	//Phases above calm are cumulative, so lockdown keeps the codes set by caution and alert active as well
+	fn enter(&mut self, phase: AlarmPhase, codes: &mut Codes)
+	{
+
+		self.phase = phase;
+		self.quiet = 0;
+		self.pressure = 0;
+
+		for (index, other) in PHASES.iter().enumerate()
+		{
+
+			let active = if *other == AlarmPhase::Calm { phase == AlarmPhase::Calm } else { phase != AlarmPhase::Calm && *other <= phase };
+
+			for code in self.phases[index].codes.iter()
+			{
+
+				if active
+				{
+
+					codes.insert(*code);
+
+				}
+				else
+				{
+
+					codes.remove(*code);
+
+				}
+
+			}
+
+		}
+
+	}
+
+	fn update(&mut self, detected: Option<AlarmPhase>, step: i32, codes: &mut Codes)
+	{
+
+		match detected
+		{
+
+			Some(phase) if phase > self.phase => self.enter(phase, codes),
+			Some(_) =>
+			{
+
+				self.quiet = 0;
+				self.pressure += step;
+
+				let escalate = self.phases[self.phase as usize].escalate;
+
+				if escalate > 0 && self.pressure >= escalate && self.phase != AlarmPhase::Lockdown
+				{
+
+					self.enter(PHASES[self.phase as usize + 1], codes);
+
+				}
+
+			},
+			None =>
+			{
+
+				self.quiet += step;
+
+				if self.phase != AlarmPhase::Calm && self.quiet >= self.phases[self.phase as usize].duration
+				{
+
+					self.enter(PHASES[self.phase as usize - 1], codes);
+
+				}
+
+			}
+
+		}
+
+	}
+
+}
+
+pub struct Walls
+{
+
+	segments: Vec<Segment>
+
+}
+
+impl Walls
+{
+
+	pub fn new() -> Walls
+	{
+
+		return Walls { segments: Vec::new() };
+
+	}
+
+}
+#[system]
+#[read_component(StaticBody)]
+#[read_component(OneWayBody)]
+#[read_component(PolygonBody)]
+fn update_wall_segments(world: &mut SubWorld, #[resource] walls: &mut Walls)
+{
+
+	walls.segments.clear();
+
+	let mut static_query = <&StaticBody>::query();
+
+	for body in static_query.iter(world)
+	{
+
+		let rect = &body.body;
+
+		walls.segments.push(Segment::new(Point { x: rect.x, y: rect.y }, Point { x: rect.right(), y: rect.y }));
+		walls.segments.push(Segment::new(Point { x: rect.right(), y: rect.y }, Point { x: rect.right(), y: rect.bottom() }));
+		walls.segments.push(Segment::new(Point { x: rect.right(), y: rect.bottom() }, Point { x: rect.x, y: rect.bottom() }));
+		walls.segments.push(Segment::new(Point { x: rect.x, y: rect.bottom() }, Point { x: rect.x, y: rect.y }));
+
+	}
+
+	let mut oneway_query = <&OneWayBody>::query();
+
+	for body in oneway_query.iter(world)
+	{
+
+		let rect = &body.body;
+
+		walls.segments.push(Segment::new(Point { x: rect.x, y: rect.y }, Point { x: rect.right(), y: rect.y }));
+		walls.segments.push(Segment::new(Point { x: rect.right(), y: rect.y }, Point { x: rect.right(), y: rect.bottom() }));
+		walls.segments.push(Segment::new(Point { x: rect.right(), y: rect.bottom() }, Point { x: rect.x, y: rect.bottom() }));
+		walls.segments.push(Segment::new(Point { x: rect.x, y: rect.bottom() }, Point { x: rect.x, y: rect.y }));
+
+	}
+
+	let mut polygon_query = <&PolygonBody>::query();
+
+	for body in polygon_query.iter(world)
+	{
+
+		for i in 0..body.points.len()
+		{
+
+			walls.segments.push(Segment::new(body.points[i], body.points[(i + 1) % body.points.len()]));
+
+		}
+
+	}
+
+}
+
+#[system(for_each)]
+fn observer_follow(observer: &mut Observer, body: &DynamicBody)
+{
+
+	observer.shift(Point { x: body.x(), y: body.y() });
+	observer.face(body.left);
+
+}
+
+#[system(for_each)]
+fn disabled_timer(observer: &mut Observer, disabled: &mut Disabled, cone: Option<&mut Cone>, cmd: &mut CommandBuffer, entity: &Entity, #[resource] time: &Timestep)
+{
+
+	observer.sighting = None;
+	observer.exposure = 0.0;
+
+	//Line of sight is not updated while disabled, so drop the old view rather than leave it trailing behind
+	if let Some(cone) = cone
+	{
+
+		cone.field.clear();
+
+	}
+
+	disabled.remaining -= time.step;
+
+	if disabled.remaining <= 0
+	{
+
+		cmd.remove_component::<Disabled>(*entity);
+
+	}
+
+}
+
+#[system(for_each)]
+#[filter(!component::<Disabled>())]
+fn line_of_sight(observer: &mut Observer, cone: &mut Cone, #[resource] walls: &Walls)
+{
+
+	let mut rays: Vec<Point> = Vec::new();
+	rays.push(observer.lower);
+	rays.push(observer.upper);
+
+	let bound_check = |ray: Point| -> bool
+	{
+
+		return ray.ray_between(&observer.lower, &observer.upper);
+		
+	};
+
+	//Collect the rays we need to project
+	for segment in walls.segments.iter()
+	{
+
+		//We only cast a ray to the start of the segment because each corner of each box will be the start of one of the segments			
+		let ray = Point { x: segment.start.x - observer.location.x, y: segment.start.y - observer.location.y };
+
+		if bound_check(ray)
+		{
+
+			rays.push(ray);
+
+		}
+
+	}
+
+	//Sort the rays from lower to upper
+	Point::sort_from_angle(&mut rays, observer.lower);
+	
+	//Actually create the triangles
+	cone.field.clear();
+
+	for i in 0..rays.len()-1
+	{
+
+		let mut shortest_current = 0.0;
+		let mut shortest_next = 0.0;
+
+		for segment in walls.segments.iter()
+		{
+
+			let cast_current = segment.raycast(observer.location, rays[i]);
+			let cast_next = segment.raycast(observer.location, rays[i + 1]);
+
+			if cast_current.is_some() && cast_next.is_some() && (shortest_current == 0.0 || cast_current.unwrap() < shortest_current)
+			{
+
+				shortest_current = cast_current.unwrap();
+				shortest_next = cast_next.unwrap();
+
+			}
+
+		}
+
+		cone.field.push(Triangle::new(observer.location, observer.location + rays[i].scale(shortest_current), observer.location + rays[i + 1].scale(shortest_next)));
+
+	}
+
+}
+
+#[system(for_each)]
+fn hidden_timer(hidden: &mut Hidden, #[resource] time: &Timestep)
+{
+
+	hidden.time += time.step;
+
+}
+
+//Disturbances with a trigger stay active from when its code is raised until they are noticed. Downed bodies are only
+//active while they are disabled, and can be noticed again the next time they are
+#[system(for_each)]
+fn disturbance_trigger(disturbance: &mut Disturbance, disabled: Option<&Disabled>, #[resource] codes: &Codes)
+{
+
+	match disturbance.trigger
+	{
+
+		Some(trigger) =>
+		{
+
+			if codes.contains(trigger)
+			{
+
+				disturbance.active = true;
+
+			}
+
+		},
+		None =>
+		{
+
+			disturbance.active = disabled.is_some();
+
+			if !disturbance.active
+			{
+
+				disturbance.noticed = false;
+
+			}
+
+		}
+
+	}
+
+}
+
+//Observers build up exposure to the most visible suspicious body in view, and only notice it once that exceeds their reaction time
+#[system(for_each)]
+#[read_component(DynamicBody)]
+#[read_component(Suspicious)]
+#[read_component(Hidden)]
+#[read_component(LowProfile)]
+#[read_component(Cover)]
+#[read_component(Submerged)]
+#[write_component(Disturbance)]
+#[filter(!component::<Disabled>())]
+fn visual_alarm(observer: &mut Observer, cone: &Cone, world: &mut SubWorld, #[resource] codes: &mut Codes, #[resource] time: &Timestep)
+{
+
+	observer.sighting = None;
+
+	let mut cover_query = <&Cover>::query();
+	let covers: Vec<&Cover> = cover_query.iter(world).collect();
+
+	let mut seen: Option<(Entity, Point, f32, &Suspicious)> = None;
+
+	let mut query = <(Entity, &Suspicious, Option<&DynamicBody>, Option<&Disturbance>, Option<&Hidden>, Option<&LowProfile>, Option<&Submerged>)>::query();
+
+	for (entity, suspicious, dynamic, disturbance, hidden, low, submerged) in query.iter(world)
+	{
+
+		let body = match (dynamic, disturbance)
+		{
+
+			(_, Some(disturbance)) if !disturbance.active() => continue,
+			(Some(dynamic), _) => dynamic.body,
+			(None, Some(disturbance)) => disturbance.rect,
+			(None, None) => continue
+
+		};
+
+		//Murky fluids hide whatever part of the body is under the surface
+		let visibility = match submerged
+		{
+
+			Some(submerged) => suspicious.visibility * (1.0 - submerged.depth * (1.0 - submerged.clarity)),
+			None => suspicious.visibility
+
+		};
+
+		let watched = observer.exposure > 0.0 && observer.watching == Some(*entity);
+
+		if (hidden.is_some_and(|h| { return h.concealed(); }) && !watched) || visibility <= 0.0
+		{
+
+			continue;
+
+		}
+
+		if low.is_some() && covers.iter().any(|c| { return c.conceals(&body, observer.location); })
+		{
+
+			continue;
+
+		}
+
+		if seen.is_some_and(|(_, _, other_visibility, other)| { return (other.priority, other_visibility) >= (suspicious.priority, visibility); })
+		{
+
+			continue;
+
+		}
+
+		if Codes::codes_interact(suspicious.code, observer.code) && cone.field.iter().any(|triangle| { return triangle.intersects_rectangle(&body); })
+		{
+
+			seen = Some((*entity, Point { x: body.x + body.width * 0.5, y: body.y + body.height * 0.5 }, visibility, suspicious));
+
+		}
+
+	}
+
+	let seen = seen.map(|(entity, point, visibility, suspicious)| { return (entity, point, visibility, suspicious.priority, suspicious.code); });
+
+	match seen
+	{
+
+		Some((entity, point, visibility, priority, code)) =>
+		{
+
+			observer.exposure = (observer.exposure + visibility * time.step as f32).min(observer.reaction as f32);
+			observer.watching = Some(entity);
+
+			if observer.exposure >= observer.reaction as f32
+			{
+
+				observer.sighting = Some(point);
+				observer.priority = priority;
+
+				//Intruders raise the observer's own code, anything else the code of whatever was noticed
+				codes.insert(if priority >= INTRUDER_PRIORITY { observer.code } else { code });
+
+				if let Ok(mut entry) = world.entry_mut(entity)
+				{
+
+					if let Ok(disturbance) = entry.get_component_mut::<Disturbance>()
+					{
+
+						disturbance.noticed = true;
+
+					}
+
+				}
+
+			}
+
+		},
+		None => observer.exposure = (observer.exposure - time.step as f32).max(0.0)
+
+	}
+
+}
+
+#[system(for_each)]
+fn tripwire_update(tripwire: &mut Tripwire, #[resource] walls: &Walls, #[resource] codes: &Codes, #[resource] time: &Timestep)
+{
+
+	tripwire.time += time.step;
+	tripwire.segments.clear();
+
+	if !tripwire.scheduled() || tripwire.disable.is_some_and(|code| { return codes.contains(code); })
+	{
+
+		return;
+
+	}
+
+	for beam in tripwire.beams.iter()
+	{
+
+		let ray = beam.end - beam.start;
+
+		//Walls the emitter is mounted on are hit immediately, so only later hits block the beam
+		let blocked = walls.segments.iter()
+			.filter_map(|wall| { return wall.raycast(beam.start, ray); })
+			.filter(|t| { return *t > FLOATING_POINT_ERROR; })
+			.fold(1.0_f32, f32::min);
+
+		tripwire.segments.push(Segment::new(beam.start, beam.start + ray.scale(blocked)));
+
+	}
+
+}
+
+//Tripwires do not care how visible a body is, only hidden bodies are safe from them. Disturbances are only ever seen
+#[system(for_each)]
+#[read_component(DynamicBody)]
+#[read_component(Suspicious)]
+#[read_component(Hidden)]
+#[read_component(Disturbance)]
+fn tripwire_alarm(tripwire: &Tripwire, world: &mut SubWorld, #[resource] codes: &mut Codes)
+{
+
+	let mut query = <(&DynamicBody, &Suspicious, Option<&Hidden>)>::query().filter(!component::<Disturbance>());
+
+	for (body, _, hidden) in query.iter(world)
+	{
+
+		if hidden.is_some_and(|h| { return h.concealed(); })
+		{
+
+			continue;
+
+		}
+
+		if tripwire.segments.iter().any(|segment| { return segment.intersects_rectangle(&body.body); })
+		{
+
+			codes.insert(tripwire.code);
+
+			return;
+
+		}
+
+	}
+
+}
+
+//Hard landings are heard by every observer with a body in earshot, who treats the noise as a sighting unless it is
+//already watching something more important. Cameras have no ears
+#[system]
+#[write_component(Noisy)]
+#[read_component(DynamicBody)]
+#[read_component(Velocity)]
+#[read_component(Contacts)]
+#[write_component(Observer)]
+#[read_component(Disabled)]
+fn hear_noise(world: &mut SubWorld, #[resource] codes: &mut Codes)
+{
+
+	let mut noisy_query = <(&mut Noisy, &DynamicBody, &Velocity, &Contacts)>::query();
+
+	let mut noises: Vec<(Point, f32, u128, i32)> = Vec::new();
+
+	for (noisy, dynamic, velocity, contacts) in noisy_query.iter_mut(world)
+	{
+
+		if contacts.grounded && !noisy.grounded && noisy.falling >= noisy.speed
+		{
+
+			noises.push((Point { x: dynamic.x() + dynamic.width() * 0.5, y: dynamic.y() + dynamic.height() * 0.5 }, noisy.radius, noisy.code, noisy.priority));
+
+		}
+
+		noisy.grounded = contacts.grounded;
+		noisy.falling = velocity.y * dynamic.down();
+
+	}
+
+	if noises.is_empty()
+	{
+
+		return;
+
+	}
+
+	let mut query = <&mut Observer>::query().filter(component::<DynamicBody>() & !component::<Disabled>());
+
+	for observer in query.iter_mut(world)
+	{
+
+		for (point, radius, code, priority) in noises.iter()
+		{
+
+			let offset = *point - observer.location;
+
+			if offset.dot(offset) > radius * radius || (observer.sighting.is_some() && observer.priority >= *priority)
+			{
+
+				continue;
+
+			}
+
+			observer.sighting = Some(*point);
+			observer.priority = *priority;
+			codes.insert(*code);
+
+		}
+
+	}
+
+}
+
+#[system]
+#[read_component(Observer)]
+#[write_component(Radio)]
+#[read_component(Disabled)]
+fn radio_broadcast(world: &mut SubWorld, #[resource] transmissions: &mut Transmissions, #[resource] time: &Timestep)
+{
+
+	let mut query = <(Entity, &Observer, &mut Radio, Option<&Disabled>)>::query();
+
+	let mut senders: Vec<(Entity, Point, Point)> = Vec::new();
+
+	for (entity, observer, radio, disabled) in query.iter_mut(world)
+	{
+
+		radio.cooldown -= time.step;
+
+		if disabled.is_some() || radio.cooldown > 0
+		{
+
+			continue;
+
+		}
+
+		//Only intruders are worth calling in, anything else the observer looks into alone
+		if let Some(sighting) = observer.sighting.filter(|_| { return observer.priority >= INTRUDER_PRIORITY; })
+		{
+
+			senders.push((*entity, observer.location(), sighting));
+			radio.cooldown = BROADCAST_INTERVAL;
+
+		}
+
+	}
+
+	if senders.is_empty()
+	{
+
+		return;
+
+	}
+
+	let mut radio_query = <(Entity, &Observer, &Radio)>::query();
+
+	for (sender, location, sighting) in senders
+	{
+
+		let transmitter = match radio_query.get(world, sender)
+		{
+
+			Ok((_, _, radio)) => radio,
+			Err(_) => continue
+
+		};
+
+		for (entity, observer, radio) in radio_query.iter(world)
+		{
+
+			if *entity != sender && transmitter.reaches(location, radio, observer.location())
+			{
+
+				transmissions.pending.push(Transmission { receiver: *entity, position: sighting, remaining: transmitter.delay });
+
+			}
+
+		}
+
+	}
+
+}
+
+//Delivered broadcasts raise the receiver's own code, as if it had seen the body itself
+#[system]
+#[write_component(Observer)]
+#[read_component(Disabled)]
+fn radio_receive(world: &mut SubWorld, #[resource] transmissions: &mut Transmissions, #[resource] codes: &mut Codes, #[resource] time: &Timestep)
+{
+
+	for transmission in transmissions.pending.iter_mut()
+	{
+
+		transmission.remaining -= time.step;
+
+	}
+
+	let mut query = <(&mut Observer, Option<&Disabled>)>::query();
+
+	for transmission in transmissions.pending.iter().filter(|t| { return t.remaining <= 0; })
+	{
+
+		if let Ok((observer, None)) = query.get_mut(world, transmission.receiver)
+		{
+
+			observer.report = Some(transmission.position);
+			codes.insert(observer.code);
+
+		}
+
+	}
+
+	transmissions.pending.retain(|t| { return t.remaining > 0; });
+
+}
+
+//Trigger codes read last step are cleared before anything can raise them again, so that continued detection has to keep
+//raising them every step. Waiting until then leaves them set for the watchers which run after the alarm systems
+#[system]
+fn alarm_reset(#[resource] controller: &mut AlarmController, #[resource] codes: &mut Codes)
+{
+
+	for code in controller.raised.drain(..)
+	{
+
+		codes.remove(code);
+
+	}
+
+}
+
+#[system]
+fn alarm_controller(#[resource] controller: &mut AlarmController, #[resource] codes: &mut Codes, #[resource] time: &Timestep)
+{
+
+	let mut detected: Option<AlarmPhase> = None;
+	let mut raised = Vec::new();
+
+	for (code, phase) in controller.triggers.iter()
+	{
+
+		if codes.contains(*code)
+		{
+
+			raised.push(*code);
+
+			detected = detected.max(Some(*phase));
+
+		}
+
+	}
+
+	controller.raised = raised;
+	controller.update(detected, time.step, codes);
+
+}
+
+pub fn schedule_alarm_systems(schedule: &mut Builder)
+{
+
+	schedule.add_system(alarm_reset_system());
+	schedule.add_system(update_wall_segments_system());
+	schedule.add_system(hidden_timer_system());
+	schedule.add_system(observer_follow_system());
+	schedule.add_system(disabled_timer_system());
+	schedule.add_system(line_of_sight_system());
+	schedule.add_system(disturbance_trigger_system());
 	schedule.add_system(visual_alarm_system());
+	schedule.add_system(hear_noise_system());
+	schedule.add_system(tripwire_update_system());
+	schedule.add_system(tripwire_alarm_system());
+	schedule.add_system(radio_broadcast_system());
+	schedule.add_system(radio_receive_system());
+	schedule.add_system(alarm_controller_system());
+
+}
+
+#[cfg(test)]
+mod tests
+{
+
+	use super::*;
+	use super::super::codes::{self, Activate, Watcher};
+	use super::super::physics::{self, Broadphase, ContactEvents, Gravity, HasGravity, StaticBody};
+
+	//Resources and a schedule which run whole steps of physics, with the given alarm systems after the bodies have moved
+	fn simulation(codes: Codes, alarms: fn(&mut Builder)) -> (Resources, Schedule)
+	{
+
+		let mut resources = Resources::default();
+
+		resources.insert(Timestep { step: 16 });
+		resources.insert(Gravity { force: 0.32, max: 16.0 });
+		resources.insert(Broadphase::new());
+		resources.insert(ContactEvents::new());
+		resources.insert(codes);
+
+		let mut builder = Schedule::builder();
+
+		physics::schedule_early_systems(&mut builder);
+		physics::schedule_physics_systems(&mut builder);
+		alarms(&mut builder);
+		physics::schedule_cleanup_systems(&mut builder);
+
+		return (resources, builder.build());
+
+	}
+
+	#[test]
+	fn noise_heard()
+	{
+
+		let mut world = World::default();
+		let mut codes = Codes::new();
+
+		let data = NoiseData { code: "noise".to_string(), priority: 25, speed: 6.0, radius: 200.0 };
+		let loud = Noisy::new(&data, &mut codes);
+		let gentle = Noisy::new(&data, &mut codes);
+		let code = codes.get_code("noise");
+
+		let (mut resources, mut schedule) = simulation(codes, |builder| { builder.add_system(hear_noise_system()); });
+
+		let origin = Point { x: 0.0, y: 0.0 };
+		let guard = |x: f32| -> (Observer, DynamicBody) { return (Observer::new(Point { x, y: 450.0 }, origin, origin, origin, 0, 300), DynamicBody::new(x, 450.0, 15.0, 50.0)); };
+
+		world.push((StaticBody { body: Rect { x: 0.0, y: 500.0, width: 1000.0, height: 20.0 } },));
+
+		let near = world.push(guard(200.0));
+		let far = world.push(guard(600.0));
+		let quiet = world.push(guard(900.0));
+		let camera = world.push((Observer::new(Point { x: 200.0, y: 450.0 }, origin, origin, origin, 0, 300),));
+
+		//A crate dropped from high enough is heard when it lands, but one set down gently is not
+		world.push((DynamicBody::new(100.0, 300.0, 20.0, 20.0), Velocity::new(0.0, 0.0), HasGravity {}, Contacts::new(), loud));
+		world.push((DynamicBody::new(880.0, 470.0, 20.0, 20.0), Velocity::new(0.0, 0.0), HasGravity {}, Contacts::new(), gentle));
+
+		let mut heard = false;
+
+		for _ in 0..60
+		{
+
+			schedule.execute(&mut world, &mut resources);
+
+			heard |= resources.get::<Codes>().unwrap().contains(code);
+			resources.get_mut::<Codes>().unwrap().remove(code);
+
+		}
+
+		assert!(heard);
+
+		let sighting = |world: &World, entity: Entity| -> Option<Point> { return world.entry_ref(entity).unwrap().get_component::<Observer>().unwrap().sighting; };
+
+		assert!(sighting(&world, near).is_some_and(|point| { return (point.y - 490.0).abs() < 0.01; }));
+		assert_eq!(sighting(&world, far), None);
+		assert_eq!(sighting(&world, quiet), None);
+		assert_eq!(sighting(&world, camera), None);
+
+	}
+
+	#[test]
+	fn downed_disturbance()
+	{
+
+		let mut world = World::default();
+		let mut resources = Resources::default();
+
+		resources.insert(Codes::new());
+
+		let mut schedule = Schedule::builder().add_system(disturbance_trigger_system()).build();
+
+		let guard = world.push((Disturbance::downed(),));
+		let active = |world: &World| -> bool { return world.entry_ref(guard).unwrap().get_component::<Disturbance>().unwrap().active(); };
+
+		schedule.execute(&mut world, &mut resources);
+
+		assert!(!active(&world));
+
+		world.entry(guard).unwrap().add_component(Disabled::new(1000));
+		schedule.execute(&mut world, &mut resources);
+
+		assert!(active(&world));
+
+		//Once noticed it is old news until the guard is back on its feet and downed again
+		world.entry(guard).unwrap().get_component_mut::<Disturbance>().unwrap().noticed = true;
+		schedule.execute(&mut world, &mut resources);
+
+		assert!(!active(&world));
+
+		world.entry(guard).unwrap().remove_component::<Disabled>();
+		schedule.execute(&mut world, &mut resources);
+		world.entry(guard).unwrap().add_component(Disabled::new(1000));
+		schedule.execute(&mut world, &mut resources);
+
+		assert!(active(&world));
+
+	}
+
+	#[test]
+	fn hid_while_watched()
+	{
+
+		let mut world = World::default();
+		let mut resources = Resources::default();
+		let mut codes = Codes::new();
+
+		let watching = codes.get_code("watching");
+		let late = codes.get_code("late");
+
+		resources.insert(Timestep { step: 16 });
+		resources.insert(codes);
+
+		let mut schedule = Schedule::builder().add_system(hidden_timer_system()).add_system(visual_alarm_system()).build();
+
+		let origin = Point { x: 0.0, y: 0.0 };
+		let view = || -> Cone { return Cone { field: vec![Triangle::new(origin, Point { x: 500.0, y: -200.0 }, Point { x: 500.0, y: 200.0 })] }; };
+
+		let player = world.push((DynamicBody::new(200.0, -25.0, 15.0, 50.0), Suspicious::new(0)));
+		world.push((Observer::new(origin, origin, origin, origin, watching, 300), view()));
+
+		//Hides just after coming into view, long before the observer could have noticed it
+		schedule.execute(&mut world, &mut resources);
+
+		world.entry(player).unwrap().add_component(Hidden::new());
+
+		//An observer which only looks once the body is concealed never sees it
+		for _ in 0..20
+		{
+
+			schedule.execute(&mut world, &mut resources);
+
+		}
+
+		world.push((Observer::new(origin, origin, origin, origin, late, 300), view()));
+
+		let mut noticed = (false, false);
+
+		for _ in 0..40
+		{
+
+			schedule.execute(&mut world, &mut resources);
+
+			let codes = resources.get::<Codes>().unwrap();
+
+			noticed = (noticed.0 || codes.contains(watching), noticed.1 || codes.contains(late));
+
+		}
+
+		assert_eq!(noticed, (true, false));
+
+	}
+
+	fn alarm(codes: &mut Codes) -> AlarmController
+	{
+
+		let phase = |duration: i32, escalate: i32, code: &str| { return PhaseData { duration, escalate, codes: vec![code.to_string()] }; };
+
+		let data = AlarmData
+		{
+
+			triggers: vec![AlarmTriggerData { code: "camera".to_string(), phase: AlarmPhase::Caution }],
+			calm: phase(0, 0, "calm"),
+			caution: phase(5000, 2000, "caution"),
+			alert: phase(10000, 4000, "alert"),
+			lockdown: phase(20000, 0, "lockdown")
+
+		};
+
+		return AlarmController::new(data, codes);
+
+	}
+
+	#[test]
+	fn alarm_phases()
+	{
+
+		let mut codes = Codes::new();
+		let mut controller = alarm(&mut codes);
+
+		let names = ["calm", "caution", "alert", "lockdown"].map(|name| { return codes.get_code(name); });
+		let active = |codes: &Codes| { return names.map(|code| { return codes.contains(code); }); };
+
+		assert_eq!(controller.phase(), AlarmPhase::Calm);
+		assert_eq!(active(&codes), [true, false, false, false]);
+
+		//Detection raises the alarm straight to the phase it triggers
+		controller.update(Some(AlarmPhase::Caution), 16, &mut codes);
+
+		assert_eq!(controller.phase(), AlarmPhase::Caution);
+		assert_eq!(active(&codes), [false, true, false, false]);
+
+		//Holds while detection continues, until it has gone on long enough to escalate
+		controller.update(Some(AlarmPhase::Caution), 1000, &mut codes);
+
+		assert_eq!(controller.phase(), AlarmPhase::Caution);
+
+		controller.update(Some(AlarmPhase::Caution), 1000, &mut codes);
+
+		assert_eq!(controller.phase(), AlarmPhase::Alert);
+		assert_eq!(active(&codes), [false, true, true, false]);
+
+		//Being detected again while decaying starts the quiet time over, even by a trigger for a lower phase
+		controller.update(None, 6000, &mut codes);
+		controller.update(Some(AlarmPhase::Caution), 16, &mut codes);
+		controller.update(None, 9000, &mut codes);
+
+		assert_eq!(controller.phase(), AlarmPhase::Alert);
+
+		//Decays one phase at a time
+		controller.update(None, 1000, &mut codes);
+
+		assert_eq!(controller.phase(), AlarmPhase::Caution);
+		assert_eq!(active(&codes), [false, true, false, false]);
+
+		controller.update(None, 5000, &mut codes);
+
+		assert_eq!(controller.phase(), AlarmPhase::Calm);
+		assert_eq!(active(&codes), [true, false, false, false]);
+
+		//Lockdown keeps every phase's codes and never escalates further
+		controller.update(Some(AlarmPhase::Lockdown), 16, &mut codes);
+		controller.update(Some(AlarmPhase::Lockdown), 100000, &mut codes);
+
+		assert_eq!(controller.phase(), AlarmPhase::Lockdown);
+		assert_eq!(active(&codes), [false, true, true, true]);
+
+	}
+
+	#[test]
+	fn alarm_triggers()
+	{
+
+		let mut codes = Codes::new();
+		let controller = alarm(&mut codes);
+		let camera = codes.get_code("camera");
+
+		let mut resources = Resources::default();
+
+		resources.insert(Timestep { step: 16 });
+		resources.insert(controller);
+		resources.insert(codes);
+
+		let mut world = World::default();
+		let mut builder = Schedule::builder();
+
+		builder.add_system(alarm_reset_system());
+		builder.add_system(alarm_controller_system());
+		codes::schedule_watcher_systems(&mut builder);
+
+		let mut schedule = builder.build();
+
+		let watcher = world.push((Watcher { code: camera, activated: false },));
+
+		resources.get_mut::<Codes>().unwrap().insert(camera);
+		schedule.execute(&mut world, &mut resources);
+
+		//Trigger codes stay set for the rest of the step, so watchers on them still fire
+		assert_eq!(resources.get::<AlarmController>().unwrap().phase(), AlarmPhase::Caution);
+		assert!(resources.get::<Codes>().unwrap().contains(camera));
+		assert!(world.entry(watcher).unwrap().get_component::<Activate>().is_ok());
+
+		//Then they are cleared, so they have to keep being raised to hold the phase
+		schedule.execute(&mut world, &mut resources);
+
+		assert!(!resources.get::<Codes>().unwrap().contains(camera));
+
+	}
+
+	#[test]
+	fn cover_sides()
+	{
+
+		let cover = Cover { rect: Rect { x: 300.0, y: 445.0, width: 40.0, height: 30.0 } };
+		let crouched = Rect { x: 310.0, y: 455.0, width: 15.0, height: 20.0 };
+		let standing = Rect { x: 310.0, y: 425.0, width: 15.0, height: 50.0 };
+		let poking = Rect { x: 290.0, y: 455.0, width: 15.0, height: 20.0 };
+
+		//Hidden from either side at about the same height, but not from above or from steeply overhead
+		assert!(cover.conceals(&crouched, Point { x: 100.0, y: 440.0 }));
+		assert!(cover.conceals(&crouched, Point { x: 600.0, y: 440.0 }));
+		assert!(!cover.conceals(&crouched, Point { x: 320.0, y: 300.0 }));
+		assert!(!cover.conceals(&crouched, Point { x: 100.0, y: 200.0 }));
+
+		//Standing up shows over the top, and a body sticking out past the side is only hidden from the far side
+		assert!(!cover.conceals(&standing, Point { x: 100.0, y: 440.0 }));
+		assert!(!cover.conceals(&poking, Point { x: 100.0, y: 440.0 }));
+		assert!(cover.conceals(&poking, Point { x: 600.0, y: 440.0 }));
+
+	}
 
 }
@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+
+use super::space::Rect;
+
+//Uniform grid of rects, so that collision checks only need to look at what is nearby rather than everything in the level
+pub struct SpatialGrid<T>
+{
+
+	size: f32,
+	cells: HashMap<(i32, i32), Vec<usize>>,
+	entries: Vec<(T, Rect)>
+
+}
+
+impl<T> SpatialGrid<T>
+{
+
+	pub fn new(size: f32) -> SpatialGrid<T>
+	{
+
+		if size <= 0.0
+		{
+
+			panic!("Cannot create a spatial grid with cells of size {}.", size);
+
+		}
+
+		return SpatialGrid { size, cells: HashMap::new(), entries: Vec::new() };
+
+	}
+
+	pub fn len(&self) -> usize
+	{
+
+		return self.entries.len();
+
+	}
+
+	pub fn is_empty(&self) -> bool
+	{
+
+		return self.entries.is_empty();
+
+	}
+
+	pub fn clear(&mut self)
+	{
+
+		self.cells.clear();
+		self.entries.clear();
+
+	}
+
+	pub fn insert(&mut self, item: T, rect: Rect)
+	{
+
+		let index = self.entries.len();
+
+		for cell in self.covered(&rect)
+		{
+
+			self.cells.entry(cell).or_default().push(index);
+
+		}
+
+		self.entries.push((item, rect));
+
+	}
+
+	//Everything whose rect overlaps or touches the area, each exactly once
+	pub fn query(&self, area: &Rect) -> Vec<&(T, Rect)>
+	{
+
+		let mut indices: Vec<usize> = self.covered(area).iter()
+			.filter_map(|cell| { return self.cells.get(cell); })
+			.flatten()
+			.copied()
+			.collect();
+
+		indices.sort_unstable();
+		indices.dedup();
+
+		return indices.into_iter()
+			.map(|i| { return &self.entries[i]; })
+			.filter(|(_, rect)| { return rect.x <= area.right() && rect.right() >= area.x && rect.y <= area.bottom() && rect.bottom() >= area.y; })
+			.collect();
+
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = &(T, Rect)>
+	{
+
+		return self.entries.iter();
+
+	}
+
+	fn covered(&self, rect: &Rect) -> Vec<(i32, i32)>
+	{
+
+		let left = (rect.x / self.size).floor() as i32;
+		let right = (rect.right() / self.size).floor() as i32;
+		let top = (rect.y / self.size).floor() as i32;
+		let bottom = (rect.bottom() / self.size).floor() as i32;
+
+		let mut cells: Vec<(i32, i32)> = Vec::new();
+
+		for x in left..=right
+		{
+
+			for y in top..=bottom
+			{
+
+				cells.push((x, y));
+
+			}
+
+		}
+
+		return cells;
+
+	}
+
+}
+
+#[cfg(test)]
+mod tests
+{
+
+	use super::*;
+
+	#[test]
+	fn grid_query()
+	{
+
+		let mut grid: SpatialGrid<usize> = SpatialGrid::new(50.0);
+
+		grid.insert(0, Rect { x: 0.0, y: 0.0, width: 20.0, height: 20.0 });
+		grid.insert(1, Rect { x: -100.0, y: 40.0, width: 300.0, height: 10.0 });
+		grid.insert(2, Rect { x: 400.0, y: 400.0, width: 10.0, height: 10.0 });
+		grid.insert(3, Rect { x: 20.0, y: 0.0, width: 10.0, height: 10.0 });
+
+		let found = |area: Rect| -> Vec<usize>
+		{
+
+			let mut items: Vec<usize> = grid.query(&area).iter().map(|(item, _)| { return *item; }).collect();
+			items.sort_unstable();
+
+			return items;
+
+		};
+
+		//Wide entries span several cells but are only returned once
+		assert_eq!(found(Rect { x: -60.0, y: 0.0, width: 120.0, height: 60.0 }), vec![0, 1, 3]);
+
+		//Touching counts, so resting contact is not missed
+		assert_eq!(found(Rect { x: 30.0, y: 0.0, width: 5.0, height: 5.0 }), vec![3]);
+		assert_eq!(found(Rect { x: 100.0, y: 30.0, width: 5.0, height: 10.0 }), vec![1]);
+
+		assert_eq!(found(Rect { x: 300.0, y: 300.0, width: 50.0, height: 50.0 }), Vec::<usize>::new());
+		assert_eq!(found(Rect { x: 405.0, y: 405.0, width: 1.0, height: 1.0 }), vec![2]);
+
+		grid.clear();
+
+		assert!(grid.is_empty());
+		assert!(grid.query(&Rect { x: 0.0, y: 0.0, width: 20.0, height: 20.0 }).is_empty());
+
+	}
+
+}
@@ -31,6 +31,13 @@ impl Codes
 
 	}
 
+	pub fn remove(&mut self, code: u128)
+	{
+
+		self.active.remove(&code);
+
+	}
+
 	pub fn contains(&self, code: u128) -> bool
 	{
 
@@ -4,7 +4,7 @@ use sfml::SfBox;
 
 use std::collections::HashMap;
 
-use super::space::{Rect, Triangle};
+use super::space::{Point, Rect, Segment, Triangle};
 use super::sprites::SpriteAtlas;
 
 pub struct Stroke
@@ -160,4 +160,40 @@ impl Draw
 
 	}
 
+	pub fn create_polygon(&self, stroke: &Stroke, points: &[Point]) -> ConvexShape<'_>
+	{
+
+		let mut polygon = ConvexShape::new(points.len() as u32);
+
+		for (i, point) in points.iter().enumerate()
+		{
+
+			polygon.set_point(i as u32, Vector2f::new(point.x, point.y));
+
+		}
+
+		polygon.set_outline_thickness(stroke.thickness);
+		polygon.set_outline_color(stroke.color);
+		polygon.set_fill_color(stroke.fill);
+
+		return polygon;
+
+	}
+
+	//Lines are thin rectangles filled with the stroke's fill colour, rotated to lie along the segment
+	pub fn create_line(&self, stroke: &Stroke, s: &Segment) -> RectangleShape<'_>
+	{
+
+		let mut line = RectangleShape::new();
+		line.set_size(Vector2f::new(s.length(), stroke.thickness));
+		line.set_origin(Vector2f::new(0.0, stroke.thickness * 0.5));
+		line.set_rotation((s.end.y - s.start.y).atan2(s.end.x - s.start.x).to_degrees());
+
+		line.set_fill_color(stroke.fill);
+		line.set_position(Vector2f::new(s.start.x, s.start.y));
+
+		return line;
+
+	}
+
 }
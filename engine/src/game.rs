@@ -10,6 +10,107 @@ pub struct Timestep
 
 }
 
+//How fast the simulation runs compared to real time. The world is always advanced in whole timesteps so that velocities,
+//timers and collision stay consistent, slow motion just runs fewer of them and fast forward runs more
+pub struct TimeScale
+{
+
+    pub scale: f32,
+    pub paused: bool,
+    pending: u32, //Single steps requested while paused
+    accumulated: f32
+
+}
+
+impl TimeScale
+{
+
+    pub const MIN_SCALE: f32 = 0.125;
+    pub const MAX_SCALE: f32 = 4.0;
+
+    pub fn new() -> TimeScale
+    {
+
+        return TimeScale { scale: 1.0, paused: false, pending: 0, accumulated: 0.0 };
+
+    }
+
+    pub fn toggle_pause(&mut self)
+    {
+
+        self.paused = !self.paused;
+        self.pending = 0;
+
+    }
+
+    //Runs exactly one more timestep, pausing first if the world was running
+    pub fn step_once(&mut self)
+    {
+
+        self.paused = true;
+        self.pending += 1;
+
+    }
+
+    pub fn set_scale(&mut self, scale: f32)
+    {
+
+        self.scale = scale.clamp(TimeScale::MIN_SCALE, TimeScale::MAX_SCALE);
+
+    }
+
+    //How many timesteps the world should be advanced by for one real timestep
+    pub fn advance(&mut self) -> u32
+    {
+
+        if self.paused
+        {
+
+            let steps = self.pending;
+            self.pending = 0;
+
+            return steps;
+
+        }
+
+        self.accumulated += self.scale;
+
+        let steps = self.accumulated.floor();
+        self.accumulated -= steps;
+
+        return steps as u32;
+
+    }
+
+    //Converts the render interpolation between real timesteps into how far the world is between its own timesteps
+    pub fn blend(&self, time: f32) -> f32
+    {
+
+        if self.paused
+        {
+
+            return 0.0;
+
+        }
+
+        return (self.accumulated + time * self.scale).min(1.0);
+
+    }
+
+}
+
+impl Default for TimeScale
+{
+
+    fn default() -> TimeScale
+    {
+
+        return TimeScale::new();
+
+    }
+
+}
+
 pub trait State
 {
 
@@ -136,3 +237,46 @@ impl Game
     }
 
 }
+
+#[cfg(test)]
+mod tests
+{
+
+    use super::*;
+
+    #[test]
+    fn time_scale_steps()
+    {
+
+        let mut time = TimeScale::new();
+
+        assert_eq!(time.advance(), 1);
+
+        //Half speed runs every other timestep, double speed runs two each time
+        time.set_scale(0.5);
+
+        assert_eq!((0..4).map(|_| { return time.advance(); }).collect::<Vec<u32>>(), vec![0, 1, 0, 1]);
+
+        time.set_scale(2.0);
+
+        assert_eq!(time.advance(), 2);
+
+        //Paused worlds only move when stepped, one step per request
+        time.toggle_pause();
+
+        assert_eq!(time.advance(), 0);
+
+        time.step_once();
+        time.step_once();
+
+        assert_eq!(time.advance(), 2);
+        assert_eq!(time.advance(), 0);
+
+        time.toggle_pause();
+        time.set_scale(100.0);
+
+        assert_eq!(time.advance(), 4);
+
+    }
+
+}
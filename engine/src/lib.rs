@@ -5,9 +5,12 @@ pub mod input;
 
 //Simulation modules
 pub mod alarm;
+pub mod broadphase;
 pub mod camera;
 pub mod codes;
+pub mod navigation;
 pub mod physics;
+pub mod rope;
 pub mod space;
 
 //Render modules
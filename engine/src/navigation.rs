@@ -0,0 +1,858 @@
+use serde::Deserialize;
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use super::physics::{Gravity, WALKABLE_NORMAL};
+use super::space::{FLOATING_POINT_ERROR, Point, Polygon, Rect};
+
+//Spacing between sampled jump takeoff points along a surface
+const JUMP_SAMPLE: f32 = 40.0;
+//Fractions of the agent's run speed tried for every jump
+const JUMP_RUNS: [f32; 5] = [-1.0, -0.5, 0.0, 0.5, 1.0];
+//Fractions of the agent's run speed tried when walking off an edge
+const FALL_RUNS: [f32; 3] = [0.25, 0.5, 1.0];
+//Longest arc simulated before a jump or fall is considered a dead end
+const MAX_AIR_STEPS: usize = 300;
+//How far below a point we look for the surface it belongs to
+const SURFACE_SEARCH: f32 = 200.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NavKind
+{
+
+	Walk,
+	Jump,
+	Fall,
+	DropThrough
+
+}
+
+//The size and movement abilities of the agents the graph is built for
+#[derive(Deserialize)]
+pub struct NavAgent
+{
+
+	pub width: f32,
+	pub height: f32,
+	pub jump_speed: f32,
+	pub run_speed: f32
+
+}
+
+//A walkable stretch along the top of a body. Points on a surface are the agent's bottom centre
+#[derive(Debug)]
+pub struct Surface
+{
+
+	pub left: f32,
+	pub right: f32,
+	pub y: f32, //Height at the left end
+	pub slope: f32, //Change in height per pixel to the right, zero for the tops of rects
+	pub oneway: bool
+
+}
+
+impl Surface
+{
+
+	fn contains(&self, x: f32) -> bool
+	{
+
+		return x >= self.left - FLOATING_POINT_ERROR && x <= self.right + FLOATING_POINT_ERROR;
+
+	}
+
+	pub fn height(&self, x: f32) -> f32
+	{
+
+		return self.y + self.slope * (x - self.left);
+
+	}
+
+	fn point(&self, x: f32) -> Point
+	{
+
+		return Point { x, y: self.height(x) };
+
+	}
+
+}
+
+//Takeoff, landing, initial velocity and cost of the best arc found between two surfaces
+type Arc = (Point, Point, (f32, f32), f32);
+
+//What arcs are simulated through: the level's solids, the agent following them and the gravity pulling it down
+struct Flight<'a>
+{
+
+	solids: &'a [Rect],
+	polygons: &'a [Polygon],
+	agent: &'a NavAgent,
+	gravity: &'a Gravity
+
+}
+
+struct Vertex
+{
+
+	surface: usize,
+	point: Point
+
+}
+
+struct Link
+{
+
+	to: usize,
+	kind: NavKind,
+	velocity: (f32, f32),
+	cost: f32
+
+}
+
+//One leg of a path. Walks go to end along the current surface, everything else leaves from start with the given velocity
+#[derive(Clone, Copy, Debug)]
+pub struct NavStep
+{
+
+	pub kind: NavKind,
+	pub start: Point,
+	pub end: Point,
+	pub velocity: (f32, f32)
+
+}
+
+pub struct NavGraph
+{
+
+	surfaces: Vec<Surface>,
+	vertices: Vec<Vertex>,
+	links: Vec<Link>,
+	on_surface: Vec<Vec<usize>>,
+	leaving: HashMap<usize, Vec<usize>>
+
+}
+
+//A graph with nowhere to go, for levels without any agents to build one for
+impl Default for NavGraph
+{
+
+	fn default() -> NavGraph
+	{
+
+		return NavGraph { surfaces: Vec::new(), vertices: Vec::new(), links: Vec::new(), on_surface: Vec::new(), leaving: HashMap::new() };
+
+	}
+
+}
+
+struct Open
+{
+
+	cost: f32,
+	vertex: usize
+
+}
+
+impl PartialEq for Open
+{
+
+	fn eq(&self, other: &Open) -> bool
+	{
+
+		return self.cost == other.cost;
+
+	}
+
+}
+
+impl Eq for Open {}
+
+impl PartialOrd for Open
+{
+
+	fn partial_cmp(&self, other: &Open) -> Option<Ordering>
+	{
+
+		return Some(self.cmp(other));
+
+	}
+
+}
+
+impl Ord for Open
+{
+
+	//Reversed so that the binary heap pops the cheapest vertex first
+	fn cmp(&self, other: &Open) -> Ordering
+	{
+
+		return other.cost.partial_cmp(&self.cost).unwrap_or(Ordering::Equal);
+
+	}
+
+}
+
+impl NavGraph
+{
+
+	//Step is the simulation timestep in milliseconds, used to convert the agent's run speed to the per-step velocities physics uses.
+	//Polygons are solid, and their edges which are shallow enough to walk on become sloped surfaces. One way polygons only give
+	//surfaces which can be dropped through, like one way platforms
+	pub fn new(solids: &[Rect], oneways: &[Rect], polygons: &[Polygon], oneway_polygons: &[Polygon], agent: &NavAgent, gravity: &Gravity, step: i32) -> NavGraph
+	{
+
+		let mut graph = NavGraph { surfaces: Vec::new(), vertices: Vec::new(), links: Vec::new(), on_surface: Vec::new(), leaving: HashMap::new() };
+
+		let bounds: Vec<Rect> = polygons.iter().map(|polygon| { return polygon.bounds(); }).collect();
+		let blockers: Vec<Rect> = solids.iter().chain(bounds.iter()).copied().collect();
+
+		for rect in solids.iter()
+		{
+
+			graph.add_surfaces(Surface { left: rect.x, right: rect.right(), y: rect.y, slope: 0.0, oneway: false }, &blockers, agent);
+
+		}
+
+		for rect in oneways.iter()
+		{
+
+			graph.add_surfaces(Surface { left: rect.x, right: rect.right(), y: rect.y, slope: 0.0, oneway: true }, &blockers, agent);
+
+		}
+
+		for (index, polygon) in polygons.iter().enumerate()
+		{
+
+			//A polygon never blocks its own edges
+			let others: Vec<Rect> = solids.iter().chain(bounds.iter().enumerate().filter(|(other, _)| { return *other != index; }).map(|(_, rect)| { return rect; })).copied().collect();
+
+			for surface in NavGraph::walkable_edges(polygon, false)
+			{
+
+				graph.add_surfaces(surface, &others, agent);
+
+			}
+
+		}
+
+		for polygon in oneway_polygons.iter()
+		{
+
+			for surface in NavGraph::walkable_edges(polygon, true)
+			{
+
+				graph.add_surfaces(surface, &blockers, agent);
+
+			}
+
+		}
+
+		graph.on_surface = vec![Vec::new(); graph.surfaces.len()];
+
+		let run = agent.run_speed * step as f32 / 1000.0;
+		let flight = Flight { solids, polygons, agent, gravity };
+
+		let mut best: HashMap<(usize, usize, NavKind, i32), Arc> = HashMap::new();
+		let mut consider = |from: usize, kind: NavKind, start: Point, arc: Option<(usize, Point, f32)>, velocity: (f32, f32)|
+		{
+
+			if let Some((to, end, cost)) = arc
+			{
+
+				let key = (from, to, kind, velocity.0.signum() as i32);
+
+				let better = match best.get(&key)
+				{
+
+					Some(existing) => cost < existing.3,
+					None => true
+
+				};
+
+				if better
+				{
+
+					best.insert(key, (start, end, velocity, cost));
+
+				}
+
+			}
+
+		};
+
+		for (index, surface) in graph.surfaces.iter().enumerate()
+		{
+
+			//Walk off either edge
+			for &direction in [-1.0, 1.0].iter()
+			{
+
+				let edge = if direction < 0.0 { surface.left } else { surface.right };
+				let start = Point { x: edge + direction * agent.width * 0.5, y: surface.height(edge) };
+
+				for fraction in FALL_RUNS.iter()
+				{
+
+					let velocity = (direction * fraction * run, 0.0);
+
+					consider(index, NavKind::Fall, start, graph.simulate(start, velocity, index, false, &flight), velocity);
+
+				}
+
+			}
+
+			//Jump from points sampled along the surface
+			let mut x = surface.left;
+
+			loop
+			{
+
+				let start = surface.point(x.min(surface.right));
+
+				for fraction in JUMP_RUNS.iter()
+				{
+
+					let velocity = (fraction * run, -agent.jump_speed);
+
+					consider(index, NavKind::Jump, start, graph.simulate(start, velocity, index, false, &flight), velocity);
+
+				}
+
+				if x >= surface.right
+				{
+
+					break;
+
+				}
+
+				x += JUMP_SAMPLE;
+
+			}
+
+			//Drop through the middle of one-way platforms
+			if surface.oneway
+			{
+
+				let start = surface.point((surface.left + surface.right) * 0.5);
+
+				consider(index, NavKind::DropThrough, start, graph.simulate(start, (0.0, 0.0), index, true, &flight), (0.0, 0.0));
+
+			}
+
+		}
+
+		//Surfaces whose ends meet can be walked between directly
+		for a in 0..graph.surfaces.len()
+		{
+
+			for b in 0..graph.surfaces.len()
+			{
+
+				let (first, second) = (&graph.surfaces[a], &graph.surfaces[b]);
+
+				if a != b && (first.height(first.right) - second.y).abs() < FLOATING_POINT_ERROR && (first.right - second.left).abs() < FLOATING_POINT_ERROR
+				{
+
+					let point = first.point(first.right);
+
+					best.insert((a, b, NavKind::Walk, 1), (point, point, (0.0, 0.0), 0.0));
+					best.insert((b, a, NavKind::Walk, -1), (point, point, (0.0, 0.0), 0.0));
+
+				}
+
+			}
+
+		}
+
+		for ((from, to, kind, _), (start, end, velocity, cost)) in best.into_iter()
+		{
+
+			let takeoff = graph.add_vertex(from, start);
+			let landing = graph.add_vertex(to, end);
+
+			graph.leaving.entry(takeoff).or_default().push(graph.links.len());
+			graph.links.push(Link { to: landing, kind, velocity, cost });
+
+		}
+
+		return graph;
+
+	}
+
+	pub fn surfaces(&self) -> &Vec<Surface>
+	{
+
+		return &self.surfaces;
+
+	}
+
+	//Split the top of a body into the stretches an agent can actually stand on, i.e. those with no solid in the way above them
+	fn add_surfaces(&mut self, surface: Surface, solids: &[Rect], agent: &NavAgent)
+	{
+
+		let top = surface.y.min(surface.height(surface.right));
+		let bottom = surface.y.max(surface.height(surface.right));
+		let mut intervals = vec![(surface.left, surface.right)];
+
+		for solid in solids.iter()
+		{
+
+			if solid.y < bottom && solid.bottom() > top - agent.height
+			{
+
+				let mut clipped = Vec::new();
+
+				for (left, right) in intervals.into_iter()
+				{
+
+					if solid.right() <= left || solid.x >= right
+					{
+
+						clipped.push((left, right));
+						continue;
+
+					}
+
+					if solid.x > left
+					{
+
+						clipped.push((left, solid.x));
+
+					}
+
+					if solid.right() < right
+					{
+
+						clipped.push((solid.right(), right));
+
+					}
+
+				}
+
+				intervals = clipped;
+
+			}
+
+		}
+
+		for (left, right) in intervals.into_iter()
+		{
+
+			if right - left > FLOATING_POINT_ERROR
+			{
+
+				self.surfaces.push(Surface { left, right, y: surface.height(left), ..surface });
+
+			}
+
+		}
+
+	}
+
+	//Edges with the polygon below them and a normal pointing far enough upwards, the same test physics uses for ground
+	fn walkable_edges(polygon: &Polygon, oneway: bool) -> Vec<Surface>
+	{
+
+		let mut points: Vec<Point> = vec![polygon.vertices[0]];
+
+		for edge in polygon.vertices[1..polygon.vertices.len() - 1].iter()
+		{
+
+			points.push(*points.last().unwrap() + *edge);
+
+		}
+
+		let centre = points.iter().fold(Point { x: 0.0, y: 0.0 }, |sum, point| { return sum + *point; }).scale(1.0 / points.len() as f32);
+
+		let mut surfaces = Vec::new();
+
+		for i in 0..points.len()
+		{
+
+			let (a, b) = (points[i], points[(i + 1) % points.len()]);
+			let (start, end) = if a.x <= b.x { (a, b) } else { (b, a) };
+
+			let width = end.x - start.x;
+			let length = (end - start).dot(end - start).sqrt();
+
+			if width < FLOATING_POINT_ERROR || width / length < WALKABLE_NORMAL
+			{
+
+				continue;
+
+			}
+
+			let slope = (end.y - start.y) / width;
+
+			if centre.y > start.y + slope * (centre.x - start.x)
+			{
+
+				surfaces.push(Surface { left: start.x, right: end.x, y: start.y, slope, oneway });
+
+			}
+
+		}
+
+		return surfaces;
+
+	}
+
+	fn add_vertex(&mut self, surface: usize, point: Point) -> usize
+	{
+
+		let index = self.vertices.len();
+
+		self.vertices.push(Vertex { surface, point });
+		self.on_surface[surface].push(index);
+
+		return index;
+
+	}
+
+	//Follow the arc physics would produce and return the surface it lands on, where, and the distance travelled.
+	//Arcs which hit a solid or come back down on the surface they left are discarded, which keeps the links conservative.
+	//Dropping through ignores the starting surface entirely
+	fn simulate(&self, start: Point, (vx, mut vy): (f32, f32), from: usize, through: bool, flight: &Flight) -> Option<(usize, Point, f32)>
+	{
+
+		let mut position = start;
+		let mut length = 0.0;
+
+		for _ in 0..MAX_AIR_STEPS
+		{
+
+			vy = (vy + flight.gravity.force).min(flight.gravity.max);
+
+			let previous = position;
+			position = Point { x: position.x + vx, y: position.y + vy };
+			length += (vx * vx + vy * vy).sqrt();
+
+			if vy > 0.0
+			{
+
+				let mut landing: Option<usize> = None;
+
+				for (index, surface) in self.surfaces.iter().enumerate()
+				{
+
+					let height = surface.height(position.x);
+					let crossed = (index != from || !through) && previous.y <= surface.height(previous.x) + FLOATING_POINT_ERROR && position.y >= height && surface.contains(position.x);
+
+					if crossed && landing.is_none_or(|l| { return height < self.surfaces[l].height(position.x); })
+					{
+
+						landing = Some(index);
+
+					}
+
+				}
+
+				if let Some(index) = landing
+				{
+
+					if index == from
+					{
+
+						return None;
+
+					}
+
+					return Some((index, self.surfaces[index].point(position.x), length));
+
+				}
+
+			}
+
+			let body = Rect { x: position.x - flight.agent.width * 0.5, y: position.y - flight.agent.height, width: flight.agent.width, height: flight.agent.height };
+
+			if flight.solids.iter().any(|solid| { return Rect::intersects(&body, solid); })
+			{
+
+				return None;
+
+			}
+
+			//A box standing on a slope always dips into it a little, so polygons are only checked against the agent's middle
+			let middle = Rect { x: position.x - 0.5, y: body.y, width: 1.0, height: body.height }.to_polygon();
+
+			if flight.polygons.iter().any(|polygon| { return Polygon::sat(&middle, polygon); })
+			{
+
+				return None;
+
+			}
+
+		}
+
+		return None;
+
+	}
+
+	//The surface a point stands on, searching a short distance below it
+	pub fn locate(&self, point: Point) -> Option<usize>
+	{
+
+		let mut found: Option<usize> = None;
+
+		for (index, surface) in self.surfaces.iter().enumerate()
+		{
+
+			let height = surface.height(point.x);
+			let below = surface.contains(point.x) && height >= point.y - FLOATING_POINT_ERROR && height - point.y <= SURFACE_SEARCH;
+
+			if below && found.is_none_or(|f| { return height < self.surfaces[f].height(point.x); })
+			{
+
+				found = Some(index);
+
+			}
+
+		}
+
+		return found;
+
+	}
+
+	//A* from one point to another. Both points are projected down onto the surface below them
+	pub fn find_path(&self, start: Point, goal: Point) -> Option<Vec<NavStep>>
+	{
+
+		let start_surface = self.locate(start)?;
+		let goal_surface = self.locate(goal)?;
+
+		let start_point = self.surfaces[start_surface].point(start.x);
+		let goal_point = self.surfaces[goal_surface].point(goal.x);
+
+		//The two extra vertices for this query sit after the graph's own
+		let start_vertex = self.vertices.len();
+		let goal_vertex = start_vertex + 1;
+
+		let surface_of = |v: usize| -> usize
+		{
+
+			if v == start_vertex { return start_surface; }
+			if v == goal_vertex { return goal_surface; }
+
+			return self.vertices[v].surface;
+
+		};
+
+		let point_of = |v: usize| -> Point
+		{
+
+			if v == start_vertex { return start_point; }
+			if v == goal_vertex { return goal_point; }
+
+			return self.vertices[v].point;
+
+		};
+
+		let distance = |a: Point, b: Point| -> f32
+		{
+
+			return ((a.x - b.x) * (a.x - b.x) + (a.y - b.y) * (a.y - b.y)).sqrt();
+
+		};
+
+		let mut costs: HashMap<usize, f32> = HashMap::new();
+		let mut came_from: HashMap<usize, (usize, Option<usize>)> = HashMap::new();
+		let mut open = BinaryHeap::new();
+
+		costs.insert(start_vertex, 0.0);
+		open.push(Open { cost: distance(start_point, goal_point), vertex: start_vertex });
+
+		while let Some(Open { vertex, .. }) = open.pop()
+		{
+
+			if vertex == goal_vertex
+			{
+
+				return Some(self.reconstruct(&came_from, goal_vertex, &point_of));
+
+			}
+
+			let cost = costs[&vertex];
+			let point = point_of(vertex);
+			let surface = surface_of(vertex);
+
+			//Walking along the current surface, or taking a link which leaves from here
+			let mut neighbours: Vec<(usize, f32, Option<usize>)> = self.on_surface[surface].iter().map(|&v| { return (v, (self.vertices[v].point.x - point.x).abs(), None); }).collect();
+
+			if surface == goal_surface
+			{
+
+				neighbours.push((goal_vertex, (goal_point.x - point.x).abs(), None));
+
+			}
+
+			if let Some(links) = self.leaving.get(&vertex)
+			{
+
+				for &l in links.iter()
+				{
+
+					neighbours.push((self.links[l].to, self.links[l].cost, Some(l)));
+
+				}
+
+			}
+
+			for (next, step_cost, link) in neighbours.into_iter()
+			{
+
+				let next_cost = cost + step_cost;
+
+				if costs.get(&next).is_none_or(|&c| { return next_cost < c; })
+				{
+
+					costs.insert(next, next_cost);
+					came_from.insert(next, (vertex, link));
+					open.push(Open { cost: next_cost + distance(point_of(next), goal_point), vertex: next });
+
+				}
+
+			}
+
+		}
+
+		return None;
+
+	}
+
+	fn reconstruct(&self, came_from: &HashMap<usize, (usize, Option<usize>)>, goal: usize, point_of: &dyn Fn(usize) -> Point) -> Vec<NavStep>
+	{
+
+		let mut steps: Vec<NavStep> = Vec::new();
+		let mut current = goal;
+
+		while let Some(&(previous, link)) = came_from.get(&current)
+		{
+
+			let start = point_of(previous);
+			let end = point_of(current);
+
+			match link
+			{
+
+				Some(l) => steps.push(NavStep { kind: self.links[l].kind, start, end, velocity: self.links[l].velocity }),
+				None => steps.push(NavStep { kind: NavKind::Walk, start, end, velocity: (0.0, 0.0) })
+
+			}
+
+			current = previous;
+
+		}
+
+		steps.reverse();
+
+		//Zero-length walks come from passing through a vertex without moving, so they carry no information
+		steps.retain(|step| { return step.kind != NavKind::Walk || (step.end.x - step.start.x).abs() > FLOATING_POINT_ERROR; });
+
+		return steps;
+
+	}
+
+}
+
+#[cfg(test)]
+mod tests
+{
+
+	use super::*;
+
+	fn agent() -> NavAgent
+	{
+
+		return NavAgent { width: 10.0, height: 20.0, jump_speed: 8.0, run_speed: 200.0 };
+
+	}
+
+	fn gravity() -> Gravity
+	{
+
+		return Gravity { force: 0.32, max: 16.0 };
+
+	}
+
+	#[test]
+	fn surfaces_clipped_by_walls()
+	{
+
+		let solids = vec![Rect { x: 0.0, y: 100.0, width: 200.0, height: 10.0 }, Rect { x: 90.0, y: 50.0, width: 20.0, height: 50.0 }];
+		let graph = NavGraph::new(&solids, &[], &[], &[], &agent(), &gravity(), 16);
+
+		let floor: Vec<&Surface> = graph.surfaces().iter().filter(|s| { return s.y == 100.0; }).collect();
+
+		assert_eq!(2, floor.len());
+		assert!(floor.iter().any(|s| { return s.left == 0.0 && s.right == 90.0; }));
+		assert!(floor.iter().any(|s| { return s.left == 110.0 && s.right == 200.0; }));
+
+	}
+
+	#[test]
+	fn path_over_wall()
+	{
+
+		let solids = vec![Rect { x: 0.0, y: 100.0, width: 300.0, height: 10.0 }, Rect { x: 140.0, y: 80.0, width: 20.0, height: 20.0 }];
+		let graph = NavGraph::new(&solids, &[], &[], &[], &agent(), &gravity(), 16);
+
+		let path = graph.find_path(Point { x: 20.0, y: 100.0 }, Point { x: 280.0, y: 90.0 }).unwrap();
+
+		assert!(path.iter().any(|s| { return s.kind == NavKind::Jump; }));
+		assert_eq!(280.0, path.last().unwrap().end.x);
+
+	}
+
+	#[test]
+	fn drop_through_oneway()
+	{
+
+		let solids = vec![Rect { x: 0.0, y: 200.0, width: 300.0, height: 10.0 }];
+		let oneways = vec![Rect { x: 0.0, y: 140.0, width: 300.0, height: 10.0 }];
+		let graph = NavGraph::new(&solids, &oneways, &[], &[], &agent(), &gravity(), 16);
+
+		let down = graph.find_path(Point { x: 150.0, y: 140.0 }, Point { x: 150.0, y: 200.0 }).unwrap();
+		assert!(down.iter().any(|s| { return s.kind == NavKind::DropThrough; }));
+
+		let up = graph.find_path(Point { x: 150.0, y: 200.0 }, Point { x: 150.0, y: 140.0 });
+		assert!(up.is_some());
+
+	}
+
+	#[test]
+	fn unreachable_ledge()
+	{
+
+		let solids = vec![Rect { x: 0.0, y: 300.0, width: 100.0, height: 10.0 }, Rect { x: 50.0, y: 50.0, width: 50.0, height: 10.0 }, Rect { x: 100.0, y: 0.0, width: 10.0, height: 310.0 }];
+		let graph = NavGraph::new(&solids, &[], &[], &[], &agent(), &gravity(), 16);
+
+		assert!(graph.find_path(Point { x: 50.0, y: 300.0 }, Point { x: 50.0, y: 50.0 }).is_none());
+		assert!(graph.find_path(Point { x: 50.0, y: 50.0 }, Point { x: 50.0, y: 300.0 }).is_some());
+
+	}
+
+	#[test]
+	fn walk_up_slope()
+	{
+
+		let solids = vec![Rect { x: 0.0, y: 200.0, width: 500.0, height: 10.0 }];
+		let ramp = Polygon::from_points(&[Point { x: 100.0, y: 200.0 }, Point { x: 250.0, y: 50.0 }, Point { x: 350.0, y: 50.0 }, Point { x: 350.0, y: 200.0 }]);
+		let graph = NavGraph::new(&solids, &[], &[ramp], &[], &agent(), &gravity(), 16);
+
+		//The floor under the ramp is covered, and the ledge is too high to jump to
+		assert!(!graph.surfaces().iter().any(|s| { return s.slope == 0.0 && s.y == 200.0 && s.contains(200.0); }));
+
+		let path = graph.find_path(Point { x: 50.0, y: 200.0 }, Point { x: 300.0, y: 50.0 }).unwrap();
+
+		assert!(path.iter().all(|s| { return s.kind == NavKind::Walk; }));
+		assert!((path[0].end.x - 100.0).abs() < FLOATING_POINT_ERROR);
+		assert_eq!(Point { x: 300.0, y: 50.0 }, path.last().unwrap().end);
+
+		//Halfway up the ramp
+		assert_eq!(graph.locate(Point { x: 175.0, y: 100.0 }).map(|s| { return graph.surfaces()[s].height(175.0); }), Some(125.0));
+
+	}
+
+}
@@ -2,11 +2,14 @@ use legion::*;
 use legion::world::SubWorld;
 use legion::systems::{Builder, CommandBuffer};
 
+use serde::Deserialize;
+
 use parametrizer::Parametrizer;
 
+use super::broadphase::SpatialGrid;
 use super::codes::{Activate, ConsumeWatcher, Watcher};
 use super::game::Timestep;
-use super::space::{FLOATING_POINT_ERROR, Rect};
+use super::space::{FLOATING_POINT_ERROR, Point, Polygon, Rect};
 
 pub struct Velocity
 {
@@ -34,628 +37,3278 @@ impl Velocity
 
     }
 
-}
-
-pub struct StaticBody
-{
+    //Move the horizontal velocity towards a target by at most the given amount, without overshooting it
+    pub fn approach_x(&mut self, target: f32, amount: f32)
+    {
 
-    pub body: Rect
+        if self.x < target
+        {
 
-}
+            self.x = (self.x + amount).min(target);
 
-pub struct OneWayBody
-{
+        }
+        else
+        {
 
-    pub body: Rect
+            self.x = (self.x - amount).max(target);
 
-}
+        }
 
-pub struct InteractsWithOneWay {}
+    }
 
-pub struct ResetOneWayInteraction
-{
+    pub fn approach_y(&mut self, target: f32, amount: f32)
+    {
 
-    time: i32,
-    duration: i32
+        if self.y < target
+        {
 
-}
+            self.y = (self.y + amount).min(target);
 
-impl ResetOneWayInteraction
-{
+        }
+        else
+        {
 
-    pub fn new(duration: i32) -> ResetOneWayInteraction
-    {
+            self.y = (self.y - amount).max(target);
 
-		return ResetOneWayInteraction { time: 0, duration: duration };
+        }
 
     }
 
 }
 
-pub struct Kinematic
+//Material of a static, one way or polygon body. Friction scales how quickly bodies standing on it can speed up and slow down,
+//so ice is well below 1, and speed scales how fast they can go, so mud is well below 1
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct Material
 {
 
-    time: i32,
-    max: i32,
-    direction: i32,
-    x_param: Parametrizer<f32>,
-    y_param: Parametrizer<f32>,
-    pub change: Velocity
+    pub friction: f32,
+    pub speed: f32
 
 }
 
-impl Kinematic
+impl Default for Material
 {
 
-    pub fn new(x_param: Parametrizer<f32>, y_param: Parametrizer<f32>, max: i32) -> Kinematic
+    fn default() -> Material
     {
 
-        return Kinematic { time: 0, max: max, x_param: x_param, y_param: y_param, direction: 1, change: Velocity { x: 0.0, y: 0.0 } };
+        return Material { friction: 1.0, speed: 1.0 };
 
     }
 
-    //Check to see if the dynamic body should be pushed by the kinematic
-    fn should_move(kinematic: &Rect, dynamic: &mut Rect) -> bool
-    {
-
-        let shift = if dynamic.y >= kinematic.y { -1.0 } else { 1.0 };
-        dynamic.y += shift;
-
-        let intersects = Rect::intersects(kinematic, dynamic);
+}
 
-        dynamic.y -= shift;
+pub struct StaticBody
+{
 
-        return intersects;
+    pub body: Rect
 
-    }
+}
 
-    fn should_move_oneway(kinematic: &Rect, dynamic: &mut Rect) -> bool
-    {
+pub struct OneWayBody
+{
 
-		let mut above = dynamic.bottom() <= kinematic.y;
+    pub body: Rect
 
-		dynamic.y += 1.0;
+}
 
-		above = above && Rect::intersects(kinematic, dynamic);
+//Solid convex geometry, such as slopes. Surfaces whose normals point far enough upwards can be walked on. One way polygons
+//only catch bodies landing on their walkable surfaces, like one way platforms
+#[derive(Clone)]
+pub struct PolygonBody
+{
 
-		dynamic.y -= 1.0;
+    pub points: Vec<Point>,
+    pub bounds: Rect,
+    pub oneway: bool,
+    polygon: Polygon
 
-		return above;	
+}
 
-    }	
+impl PolygonBody
+{
 
-    fn update(&mut self, step: i32) -> (f32, f32)
+    pub fn new(points: Vec<Point>) -> PolygonBody
     {
 
-        self.time += step * self.direction;
-
-        let t = self.time as f32 / 1000.0;
+        let polygon = Polygon::from_points(&points);
+        let bounds = polygon.bounds();
 
-        let x = self.x_param.evaluate(t);
-        let y = self.y_param.evaluate(t);
-
-        return (x, y);
+        return PolygonBody { points, bounds, oneway: false, polygon };
 
     }
 
-    fn reverse(&mut self)
+    pub fn with_oneway(mut self, oneway: bool) -> PolygonBody
     {
 
-        self.direction *= -1;
+        self.oneway = oneway;
+
+        return self;
 
     }
 
-    fn stop(&mut self)
+    fn overlap(&self, body: &Rect) -> Option<Point>
     {
 
-        if self.time >= self.max
+        if !Rect::intersects(body, &self.bounds)
         {
 
-            self.time = self.max;
-            self.direction = 0;
+            return None;
 
         }
-        else if self.time <= 0
-        {
-
-            self.time = 0;
-            self.direction = 0;
 
-        }
+        return Polygon::mtv(&body.to_polygon(), &self.polygon);
 
     }
 
-    fn start(&mut self, direction: i32)
-    {
-
-        self.direction = direction;
+}
 
-    }
+//Surfaces steeper than this, measured as the upward component of their normal, are treated as walls rather than floors
+pub const WALKABLE_NORMAL: f32 = 0.5;
 
-}
+//Named collision layers. Bodies without Layers are on the world layer and collide with everything
+pub const LAYER_WORLD: u32 = 1;
+pub const LAYER_PLAYER: u32 = 1 << 1;
+pub const LAYER_GUARD: u32 = 1 << 2;
+pub const LAYER_CRATE: u32 = 1 << 3;
+pub const LAYER_PROJECTILE: u32 = 1 << 4;
+pub const LAYER_ALL: u32 = u32::MAX;
 
-pub struct DynamicBody
+//Which layers a body is on, and which layers it collides with. Two bodies only collide if each is on a layer the other's mask allows
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Layers
 {
 
-    pub body: Rect,
-    pub left: bool,
-	pub top_collision: i32,
-	pub temp_velocity: Velocity //Used to capture velocity added per frame from being moved by a moving platform. Necessary for accurate collisions
+    pub layer: u32,
+    pub mask: u32
 
 }
 
-impl DynamicBody
+impl Layers
 {
 
-    pub fn new(x: f32, y: f32, width: f32, height: f32) -> DynamicBody
+    pub fn new(layer: u32, mask: u32) -> Layers
     {
 
-        return DynamicBody { body: Rect { x, y, width, height }, left: false, top_collision: 1, temp_velocity: Velocity::new(0.0, 0.0) };
+        return Layers { layer, mask };
 
     }
 
-    pub fn x(&self) -> f32
+    //Build layers from the names used in level data. Missing lists fall back to the defaults
+    pub fn from_names(layer: &Option<Vec<String>>, mask: &Option<Vec<String>>) -> Layers
     {
 
-        return self.body.x;
+        return Layers::default().with_names(layer, mask);
 
     }
 
-    pub fn y(&self) -> f32
+    //Replace the layer or mask with the ones named in level data, keeping these for any list that is missing
+    pub fn with_names(self, layer: &Option<Vec<String>>, mask: &Option<Vec<String>>) -> Layers
     {
 
-        return self.body.y;
+        let bits = |names: &Option<Vec<String>>, default: u32| -> u32
+        {
+
+            return names.as_ref().map_or(default, |n| { return n.iter().fold(0, |bits, name| { return bits | Layers::named(name); }); });
+
+        };
+
+        return Layers { layer: bits(layer, self.layer), mask: bits(mask, self.mask) };
 
     }
 
-    pub fn width(&self) -> f32
+    fn named(name: &str) -> u32
     {
 
-        return self.body.width;
+        match name
+        {
+
+            "world" => return LAYER_WORLD,
+            "player" => return LAYER_PLAYER,
+            "guard" => return LAYER_GUARD,
+            "crate" => return LAYER_CRATE,
+            "projectile" => return LAYER_PROJECTILE,
+            "all" => return LAYER_ALL,
+            _ => panic!("Unknown collision layer {}.", name)
+
+        }
 
     }
 
-    pub fn height(&self) -> f32
+    pub fn or_default(layers: Option<&Layers>) -> Layers
     {
 
-        return self.body.height;
+        return layers.copied().unwrap_or_default();
 
     }
 
-    pub fn right(&self) -> f32
+    pub fn interact(a: &Layers, b: &Layers) -> bool
     {
 
-        return self.body.right();
+        return (a.layer & b.mask) != 0 && (b.layer & a.mask) != 0;
 
     }
 
 }
 
-pub struct RequestSizeChange
+impl Default for Layers
 {
 
-	pub width: f32,
-	pub height: f32
-
-}
-
-pub struct RequestSizeChangeSuccess {}
-pub struct RequestSizeChangeFailure {}
+    fn default() -> Layers
+    {
 
-pub struct Gravity
-{
+        return Layers { layer: LAYER_WORLD, mask: LAYER_ALL };
 
-    pub force: f32,
-    pub max: f32
+    }
 
 }
 
-pub struct HasGravity {}
+pub struct InteractsWithOneWay {}
 
-#[system(for_each)]
-fn reset_temp_velocity(dynamic: &mut DynamicBody)
+pub struct ResetOneWayInteraction
 {
 
-	dynamic.temp_velocity = Velocity::new(0.0, 0.0);
+    time: i32,
+    duration: i32
 
 }
 
-#[system(for_each)]
-fn kinematic_toggle(kinematic: &mut Kinematic, _activate: &Activate, cmd: &mut CommandBuffer, entity: &Entity)
+impl ResetOneWayInteraction
 {
 
-    if kinematic.time >= kinematic.max
+    pub fn new(duration: i32) -> ResetOneWayInteraction
     {
 
-        kinematic.start(-1);
+		return ResetOneWayInteraction { time: 0, duration: duration };
 
     }
-    else if kinematic.time <= 0
-    {
 
-        kinematic.start(1);
+}
 
-    }
+//How a kinematic body plays its path. Toggled bodies run when activated and are halted at either end by their watchers,
+//looping bodies wrap back to the start, ping pong bodies run back and forth forever and one shot bodies run once and stop
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+pub enum Motion
+{
 
-    cmd.remove_component::<Activate>(*entity);
+    Toggle,
+    Loop,
+    PingPong,
+    OneShot
 
 }
 
-#[system(for_each)]
-fn kinematic_stop(kinematic: &mut Kinematic, watcher: &Watcher)
+//What a kinematic body does when it would squeeze a dynamic body into a static one. Pushing bodies shove it along and leave
+//collision to sort it out, as platforms always have. Stopped bodies wait until the way is clear, reversed bodies turn round,
+//and crushing bodies carry on and leave the squeezed body with a Crushed component
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+pub enum Crush
 {
 
-    if !watcher.activated
-    {
+    Push,
+    Stop,
+    Reverse,
+    Crush
 
-        kinematic.stop();
+}
 
-    }
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+pub enum Easing
+{
+
+    Linear,
+    In,
+    Out,
+    InOut
 
 }
 
-#[system(for_each)]
-#[filter(!component::<Activate>())]
-fn kinematic_consume_stop(kinematic: &mut Kinematic, _watcher: &ConsumeWatcher)
+impl Easing
 {
 
-    if kinematic.time <= 0 || kinematic.time >= kinematic.max
+    fn apply(&self, t: f32) -> f32
     {
 
-        kinematic.stop();
+        match self
+        {
+
+            Easing::Linear => return t,
+            Easing::In => return t * t,
+            Easing::Out => return 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::InOut => return t * t * (3.0 - 2.0 * t)
+
+        }
 
     }
 
 }
 
-#[system(for_each)]
-#[write_component(DynamicBody)]
-fn kinematic_static_move(kinematic: &mut Kinematic, static_body: &mut StaticBody, world: &mut SubWorld, #[resource] time: &Timestep)
+//A point on a kinematic path, along with the speed in pixels per second and easing of the leg leaving it
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct Waypoint
 {
 
-    let old_x = static_body.body.x;
-    let old_y = static_body.body.y;
-
-    let new_pos = kinematic.update(time.step);
-
-    let velocity = (new_pos.0 - old_x, new_pos.1 - old_y);
-    kinematic.change = Velocity { x: velocity.0, y: velocity.1 };
+    pub point: Point,
+    pub speed: f32,
+    pub easing: Option<Easing>
 
-    let mut query = <&mut DynamicBody>::query();
-
-    for body in query.iter_mut(world)
-    {
-
-        //Move along with the platform as necessary
-        if Kinematic::should_move(&static_body.body, &mut body.body)
-        {
+}
 
-            body.body.translate(velocity);
-			body.temp_velocity.add(velocity);
+struct Leg
+{
 
-        }
-        else
-        {
+    start: Point,
+    end: Point,
+    duration: i32,
+    easing: Easing
 
-            static_body.body.x = new_pos.0;
-            static_body.body.y = new_pos.1;
+}
 
-            let correction = Rect::collides(&body.body, &static_body.body, (-velocity.0, -velocity.1));
-            body.body.translate(correction); 
+enum Path
+{
 
-            static_body.body.x = old_x;
-            static_body.body.y = old_y;
+    Parametrized(Parametrizer<f32>, Parametrizer<f32>),
+    Waypoints(Vec<Leg>)
 
-        }
+}
 
-    }
+pub struct Kinematic
+{
 
-    static_body.body.x = new_pos.0;
-    static_body.body.y = new_pos.1;
+    time: i32,
+    max: i32,
+    direction: i32,
+    path: Path,
+    motion: Motion,
+    crush: Crush,
+    pause: i32, //How long to wait at each end of the path, in milliseconds
+    wait: i32,
+    pub change: Velocity
 
 }
 
-#[system(for_each)]
-#[write_component(DynamicBody)]
-#[read_component(InteractsWithOneWay)]
-fn kinematic_oneway_move(kinematic: &mut Kinematic, oneway_body: &mut OneWayBody, world: &mut SubWorld, #[resource] time: &Timestep)
+impl Kinematic
 {
 
-    let old_x = oneway_body.body.x;
-    let old_y = oneway_body.body.y;
-
-    let new_pos = kinematic.update(time.step);
+    pub fn new(x_param: Parametrizer<f32>, y_param: Parametrizer<f32>, max: i32) -> Kinematic
+    {
 
-    let velocity = (new_pos.0 - old_x, new_pos.1 - old_y);
-    kinematic.change = Velocity { x: velocity.0, y: velocity.1 };
+        return Kinematic { time: 0, max: max, path: Path::Parametrized(x_param, y_param), motion: Motion::Toggle, crush: Crush::Push, pause: 0, wait: 0, direction: 1, change: Velocity { x: 0.0, y: 0.0 } };
 
-    let mut query = <(&mut DynamicBody, &InteractsWithOneWay)>::query();
+    }
 
-    for(body, _) in query.iter_mut(world)
+    //Looping paths get an extra leg from the last waypoint back to the first, so that the body travels round a circuit
+    pub fn from_waypoints(waypoints: &[Waypoint], looped: bool) -> Kinematic
     {
 
-        //Move along with the platform as necessary
-        if Kinematic::should_move_oneway(&oneway_body.body, &mut body.body)
+        if waypoints.len() < 2
         {
 
-            body.body.translate(velocity);
-			body.temp_velocity.add(velocity);
+            panic!("Cannot create a kinematic path with fewer than two waypoints.");
 
         }
-        else
+
+        let mut stops: Vec<&Waypoint> = waypoints.iter().collect();
+
+        if looped
         {
 
-            oneway_body.body.x = new_pos.0;
-            oneway_body.body.y = new_pos.1;
+            stops.push(&waypoints[0]);
+
+        }
+
+        let legs: Vec<Leg> = stops.windows(2).map(|pair|
+        {
 
-            let correction = Rect::collides(&body.body, &oneway_body.body, (-velocity.0, -velocity.1)); 
+            let (from, to) = (pair[0], pair[1]);
 
-            if correction.1 < 0.0 && body.body.bottom() + velocity.1 <= oneway_body.body.y
+            if from.speed <= 0.0
             {
 
-                body.body.y += correction.1;
+                panic!("Cannot create a kinematic path with a speed of {}.", from.speed);
 
             }
 
-            oneway_body.body.x = old_x;
-            oneway_body.body.y = old_y;
+            let offset = to.point - from.point;
+            let duration = ((offset.dot(offset).sqrt() / from.speed * 1000.0).round() as i32).max(1);
 
-        }
+            return Leg { start: from.point, end: to.point, duration, easing: from.easing.unwrap_or(Easing::Linear) };
 
-    }
+        }).collect();
 
-    oneway_body.body.x = new_pos.0;
-    oneway_body.body.y = new_pos.1;
+        let max = legs.iter().map(|leg| { return leg.duration; }).sum();
 
-}
-#[system(for_each)]
-fn velocity(dynamic_body: &mut DynamicBody, velocity: &Velocity)
-{
+        return Kinematic { time: 0, max, path: Path::Waypoints(legs), motion: Motion::Toggle, crush: Crush::Push, pause: 0, wait: 0, direction: 1, change: Velocity { x: 0.0, y: 0.0 } };
 
-    dynamic_body.body.translate((velocity.x, velocity.y));
+    }
 
-}
+    //Anything other than a toggled body is left still until activated, unless it should start out already moving
+    pub fn with_motion(mut self, motion: Motion, pause: i32, moving: bool) -> Kinematic
+    {
 
-#[system(for_each)]
-fn facing(dynamic_body: &mut DynamicBody, velocity: &Velocity)
-{
+        if motion == Motion::Loop && self.max <= 0
+        {
 
-    if velocity.x < -FLOATING_POINT_ERROR
-    {
+            panic!("Cannot loop a kinematic path which lasts {} milliseconds.", self.max);
 
-        dynamic_body.left = true;
+        }
+
+        self.motion = motion;
+        self.pause = pause;
+        self.direction = if moving || motion == Motion::Toggle { 1 } else { 0 };
+
+        return self;
 
     }
-    else if velocity.x > FLOATING_POINT_ERROR
+
+    pub fn with_crush(mut self, crush: Crush) -> Kinematic
     {
 
-        dynamic_body.left = false;
+        self.crush = crush;
+
+        return self;
 
     }
 
-}
+    //Check to see if the dynamic body should be pushed by the kinematic
+    fn should_move(kinematic: &Rect, dynamic: &mut Rect) -> bool
+    {
 
-#[system(for_each)]
-fn gravity(velocity: &mut Velocity, _g: &HasGravity, #[resource] gravity: &Gravity)
-{
+        let shift = if dynamic.y >= kinematic.y { -1.0 } else { 1.0 };
+        dynamic.y += shift;
 
-    velocity.y = (velocity.y + gravity.force).min(gravity.max);
+        let intersects = Rect::intersects(kinematic, dynamic);
 
-}
+        dynamic.y -= shift;
 
-#[system(for_each)]
-fn reset_oneway(reset: &mut ResetOneWayInteraction, cmd: &mut CommandBuffer, entity: &Entity, #[resource] time: &Timestep)
-{
+        return intersects;
 
-    reset.time += time.step;
+    }
 
-    if reset.time >= reset.duration
+    //Bodies with flipped gravity ride on the underside instead
+    fn should_move_oneway(kinematic: &Rect, dynamic: &mut Rect, flipped: bool) -> bool
     {
 
-		cmd.remove_component::<ResetOneWayInteraction>(*entity);
-		cmd.add_component(*entity, InteractsWithOneWay {});
+		let mut above = if flipped { dynamic.y >= kinematic.bottom() } else { dynamic.bottom() <= kinematic.y };
+		let shift = if flipped { -1.0 } else { 1.0 };
 
-    }
+		dynamic.y += shift;
 
-}
+		above = above && Rect::intersects(kinematic, dynamic);
 
-#[system(for_each)]
-fn top_collision(body: &mut DynamicBody, #[resource] time: &Timestep)
-{
-
-    body.top_collision += time.step;
+		dynamic.y -= shift;
 
-}
+		return above;	
 
-#[system(for_each)]
-#[read_component(StaticBody)]
-fn static_collision(dynamic_body: &mut DynamicBody, velocity: &mut Velocity, world: &mut SubWorld)
-{
+    }	
 
-    let mut query = <&StaticBody>::query();
+    fn save(&self) -> (i32, i32, i32)
+    {
 
-    let mut top = false;
+        return (self.time, self.direction, self.wait);
 
-	let mut collision_velocity = (velocity.x + dynamic_body.temp_velocity.x, velocity.y); //Currently unclear why adding temp_velocity.y breaks everything, but it does. Will revisit if necessary
+    }
 
-    for body in query.iter(world)
+    //Applies this step's pushes unless one of them would squeeze a body into something solid the kinematic should not crush
+    //it against, in which case the kinematic goes back to where it was. Returns whether the kinematic moved. Pushing kinematics
+    //have no blockers to check
+    fn squeeze(&mut self, pushes: Vec<KinematicPush>, (time, direction, wait): (i32, i32, i32), entity: Entity, cmd: &mut CommandBuffer, blockers: Option<Blockers>) -> bool
     {
 
-        let correction = Rect::collides(&dynamic_body.body, &body.body, collision_velocity);  
+        //Bodies pushed along together cannot be squeezed against each other
+        let pushed: Vec<Entity> = pushes.iter().filter(|(_, _, shift, _, _)| { return *shift != (0.0, 0.0); }).map(|(other, _, _, _, _)| { return *other; }).collect();
 
-        dynamic_body.body.translate(correction);
-        velocity.add(correction);
-		collision_velocity.0 += correction.0;
-		collision_velocity.1 += correction.1;
+        let crushed: Vec<Entity> = match blockers
+        {
 
-        if correction.1 != 0.0
+            Some(blockers) => pushes.iter()
+                .filter(|(_, body, shift, _, layers)| { return blockers.crushes(body, *shift, layers, entity, &pushed); })
+                .map(|(other, _, _, _, _)| { return *other; })
+                .collect(),
+            None => Vec::new()
+
+        };
+
+        if !crushed.is_empty() && self.crush != Crush::Crush
         {
 
-            velocity.y = 0.0;
-			collision_velocity.1 = 0.0;
+            self.time = time;
+            self.direction = direction;
+            self.wait = wait;
+            self.change = Velocity { x: 0.0, y: 0.0 };
+
+            if self.crush == Crush::Reverse
+            {
+
+                self.reverse();
+
+            }
+
+            return false;
 
         }
 
-        if correction.1 < 0.0
+        for (_, body, shift, carried, _) in pushes
         {
 
-            top = true;
+            body.body.translate(shift);
+
+            if carried
+            {
+
+                body.temp_velocity.add(shift);
+
+            }
+
+        }
+
+        for other in crushed
+        {
+
+            cmd.add_component(other, Crushed { by: entity });
 
         }
 
+        return true;
+
     }
 
-    if top
+    fn update(&mut self, step: i32) -> (f32, f32)
     {
 
-        dynamic_body.top_collision = 0;
+        if self.wait > 0
+        {
+
+            self.wait -= step;
+
+            return self.position();
+
+        }
+
+        self.time += step * self.direction;
+
+        match self.motion
+        {
+
+            Motion::Toggle => {},
+            Motion::Loop =>
+            {
+
+                if self.time >= self.max || self.time < 0
+                {
+
+                    self.time = self.time.rem_euclid(self.max);
+                    self.wait = self.pause;
+
+                }
+
+            },
+            Motion::PingPong =>
+            {
+
+                if (self.time >= self.max && self.direction > 0) || (self.time <= 0 && self.direction < 0)
+                {
+
+                    self.time = self.time.clamp(0, self.max);
+                    self.wait = self.pause;
+                    self.reverse();
+
+                }
+
+            },
+            Motion::OneShot =>
+            {
+
+                if self.time >= self.max
+                {
+
+                    self.time = self.max;
+                    self.direction = 0;
+
+                }
+
+            }
+
+        }
+
+        return self.position();
 
     }
 
-}
+    fn position(&self) -> (f32, f32)
+    {
 
-#[system(for_each)]
-#[read_component(OneWayBody)]
-fn oneway_collision(dynamic_body: &mut DynamicBody, velocity: &mut Velocity, _interacts: &InteractsWithOneWay, world: &mut SubWorld)
-{
+        match &self.path
+        {
 
-    let mut query = <&OneWayBody>::query();
+            Path::Parametrized(x_param, y_param) =>
+            {
 
-    let mut top = false;
+                let t = self.time as f32 / 1000.0;
 
-	let mut collision_velocity = (velocity.x + dynamic_body.temp_velocity.x, velocity.y + dynamic_body.temp_velocity.y);
+                return (x_param.evaluate(t), y_param.evaluate(t));
+
+            },
+            Path::Waypoints(legs) =>
+            {
+
+                let mut time = self.time.clamp(0, self.max);
+
+                for leg in legs.iter()
+                {
+
+                    if time <= leg.duration
+                    {
+
+                        let t = leg.easing.apply(time as f32 / leg.duration as f32);
+                        let point = leg.start + (leg.end - leg.start).scale(t);
+
+                        return (point.x, point.y);
+
+                    }
+
+                    time -= leg.duration;
+
+                }
+
+                let last = legs.last().unwrap().end;
+
+                return (last.x, last.y);
+
+            }
+
+        }
+
+    }
 
-    for body in query.iter(world)
+    fn reverse(&mut self)
     {
 
-        let correction = Rect::collides(&dynamic_body.body, &body.body, collision_velocity);
+        self.direction *= -1;
+
+    }
+
+    fn stop(&mut self)
+    {
 
-        if correction.1 < 0.0 && dynamic_body.body.bottom() - collision_velocity.1 <= body.body.y
+        if self.time >= self.max
         {
 
-            dynamic_body.body.y += correction.1;
-            velocity.y = 0.0;
-			collision_velocity.1 = 0.0;
+            self.time = self.max;
+            self.direction = 0;
 
-            top = true;
+        }
+        else if self.time <= 0
+        {
+
+            self.time = 0;
+            self.direction = 0;
 
         }
 
     }
 
-    if top
+    fn start(&mut self, direction: i32)
     {
 
-        dynamic_body.top_collision = 0;
+        self.direction = direction;
 
     }
 
 }
 
-#[system(for_each)]
-#[read_component(StaticBody)]
-#[read_component(OneWayBody)]
-fn request_size_change(dynamic_body: &mut DynamicBody, request: &RequestSizeChange, world: &mut SubWorld, cmd: &mut CommandBuffer, entity: &Entity)
+//Added to dynamic bodies which a kinematic body has squeezed into a static body, for the step it happened
+pub struct Crushed
+{
+
+    pub by: Entity
+
+}
+
+type KinematicPush<'a> = (Entity, &'a mut DynamicBody, (f32, f32), bool, Layers);
+
+//Everything a body can be squeezed or pinned against: static bodies, polygons which are not one way and solids which cannot be pushed
+struct Blockers<'a>
 {
 
-	let new_rect = Rect { x: dynamic_body.body.x, y: dynamic_body.body.y + (dynamic_body.body.height - request.height), width: request.width, height: request.height };
+    rects: Vec<(Entity, Rect, Layers)>,
+    polygons: Vec<(&'a PolygonBody, Layers)>
 
-	let mut allowed = true;
+}
 
-	let mut query = <&StaticBody>::query();
+impl<'a> Blockers<'a>
+{
 
-	for body in query.iter(world)
-	{
+    //Kinematic static bodies and solids which cannot be pushed. These can move during a step, so are gathered where they are
+    //now rather than taken from the broadphase
+    fn moving(world: &SubWorld) -> Vec<(Entity, Rect, Layers)>
+    {
 
-		if Rect::intersects(&new_rect, &body.body)
-		{
+        let mut kinematic_query = <(Entity, &StaticBody, Option<&Layers>)>::query().filter(component::<Kinematic>());
+        let mut solid_query = <(Entity, &DynamicBody, &Solid, Option<&Layers>)>::query();
 
-			allowed = false;
-			break;
+        let mut moving: Vec<(Entity, Rect, Layers)> = kinematic_query.iter(world).map(|(entity, body, layers)| { return (*entity, body.body, Layers::or_default(layers)); }).collect();
 
-		}
+        moving.extend(solid_query.iter(world)
+            .filter(|(_, _, solid, _)| { return !solid.pushable; })
+            .map(|(entity, body, _, layers)| { return (*entity, body.body, Layers::or_default(layers)); }));
 
-	}
+        return moving;
 
-	if allowed
-	{
+    }
 
-		dynamic_body.body = Rect { x: new_rect.x, y: new_rect.y, width: new_rect.width, height: new_rect.height };
+    //Whatever overlaps or touches the area. Bodies which never move and polygons come from the broadphase
+    fn within(area: &Rect, moving: &[(Entity, Rect, Layers)], broadphase: &Broadphase, world: &'a SubWorld) -> Blockers<'a>
+    {
 
-		cmd.add_component(*entity, RequestSizeChangeSuccess {});
+        let area = area.expand(FLOATING_POINT_ERROR);
 
-	}
-	else
-	{
+        let mut rects: Vec<(Entity, Rect, Layers)> = broadphase.statics.query(&area).into_iter().map(|(entry, rect)| { return (entry.entity, *rect, entry.layers); }).collect();
+        rects.extend(moving.iter().filter(|(_, rect, _)| { return Rect::intersects(&area, rect); }).copied());
 
-		cmd.add_component(*entity, RequestSizeChangeFailure {});
+        let polygons = broadphase.polygons.query(&area).into_iter()
+            .filter_map(|(entry, _)| { return world.entry_ref(entry.entity).ok()?.into_component::<PolygonBody>().ok().map(|polygon| { return (polygon, entry.layers); }); })
+            .filter(|(polygon, _)| { return !polygon.oneway; })
+            .collect();
 
-	}
+        return Blockers { rects, polygons };
 
-	cmd.remove_component::<RequestSizeChange>(*entity);
+    }
 
-}
+    //Whatever the pushed bodies could be squeezed against, from where they are to where they would be pushed
+    fn around(pushes: &[KinematicPush], moving: &[(Entity, Rect, Layers)], broadphase: &Broadphase, world: &'a SubWorld) -> Blockers<'a>
+    {
 
-#[system(for_each)]
-fn resize_failure(_fail: &RequestSizeChangeFailure, cmd: &mut CommandBuffer, entity: &Entity)
-{
+        let area = pushes.iter()
+            .map(|(_, body, shift, _, _)|
+            {
 
-	cmd.remove_component::<RequestSizeChangeFailure>(*entity);
+                let mut moved = body.body;
+                moved.translate(*shift);
+
+                return Rect::union(&body.body, &moved);
+
+            })
+            .reduce(|a, b| { return Rect::union(&a, &b); });
+
+        match area
+        {
+
+            Some(area) => return Blockers::within(&area, moving, broadphase, world),
+            None => return Blockers { rects: Vec::new(), polygons: Vec::new() }
+
+        }
+
+    }
+
+    //Whether pushing the body would shove it into something it was not already inside
+    fn crushes(&self, body: &DynamicBody, shift: (f32, f32), layers: &Layers, kinematic: Entity, pushed: &[Entity]) -> bool
+    {
+
+        if shift == (0.0, 0.0)
+        {
+
+            return false;
+
+        }
+
+        let mut ignore = pushed.to_vec();
+        ignore.push(kinematic);
+
+        return self.blocks(body, shift, layers, &ignore);
+
+    }
+
+    //Whether moving the body would take it into something it was not already inside, other than the bodies ignored. Slopes
+    //the body would just slide up do not count
+    fn blocks(&self, body: &DynamicBody, shift: (f32, f32), layers: &Layers, ignore: &[Entity]) -> bool
+    {
+
+        let mut moved = body.body;
+        moved.translate(shift);
+
+        let rects = self.rects.iter()
+            .filter(|(entity, _, other)| { return !ignore.contains(entity) && Layers::interact(layers, other); })
+            .any(|(_, rect, _)| { return Rect::intersects(&moved, rect) && !Rect::intersects(&body.body, rect); });
+
+        let polygons = self.polygons.iter()
+            .filter(|(_, other)| { return Layers::interact(layers, other); })
+            .any(|(polygon, _)|
+            {
+
+                if polygon.overlap(&body.body).is_some()
+                {
+
+                    return false;
+
+                }
+
+                return polygon.overlap(&moved).is_some_and(|mtv|
+                {
+
+                    let length = mtv.dot(mtv).sqrt();
+
+                    return length >= FLOATING_POINT_ERROR && -mtv.y * body.down() / length < WALKABLE_NORMAL;
+
+                });
+
+            });
+
+        return rects || polygons;
+
+    }
 
 }
 
-#[system(for_each)]
-fn resize_success(_success: &RequestSizeChangeSuccess, cmd: &mut CommandBuffer, entity: &Entity)
+pub struct DynamicBody
 {
 
-	cmd.remove_component::<RequestSizeChangeSuccess>(*entity);
+    pub body: Rect,
+    pub left: bool,
+    pub gravity: BodyGravity, //Worked out each step by the gravity system. Flipped gravity treats the body's top as its bottom
+	pub top_collision: i32,
+	pub temp_velocity: Velocity //Used to capture velocity added per frame from being moved by a moving platform. Necessary for accurate collisions
 
 }
 
-pub fn schedule_early_systems(schedule: &mut Builder)
+impl DynamicBody
 {
 
-    schedule.add_system(kinematic_toggle_system());
-    schedule.add_system(kinematic_stop_system());
-    schedule.add_system(kinematic_consume_stop_system());
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> DynamicBody
+    {
 
-	schedule.add_system(reset_temp_velocity_system());
-	schedule.add_system(reset_oneway_system());
-	schedule.add_system(top_collision_system());
+        return DynamicBody { body: Rect { x, y, width, height }, left: false, gravity: BodyGravity::default(), top_collision: 1, temp_velocity: Velocity::new(0.0, 0.0) };
 
-}
+    }
 
-pub fn schedule_physics_systems(schedule: &mut Builder)
-{
+    pub fn x(&self) -> f32
+    {
 
-	schedule.add_system(kinematic_static_move_system());
-    schedule.add_system(kinematic_oneway_move_system());
+        return self.body.x;
 
-    schedule.add_system(gravity_system());
+    }
 
-    schedule.add_system(velocity_system());
-    schedule.add_system(facing_system());
+    pub fn y(&self) -> f32
+    {
 
-    schedule.add_system(static_collision_system());
-    schedule.add_system(oneway_collision_system());
+        return self.body.y;
 
-	schedule.add_system(request_size_change_system());
+    }
 
-}
+    pub fn width(&self) -> f32
+    {
 
-pub fn schedule_cleanup_systems(schedule: &mut Builder)
-{
+        return self.body.width;
 
-	schedule_request_systems(schedule);
+    }
+
+    pub fn height(&self) -> f32
+    {
+
+        return self.body.height;
+
+    }
+
+    pub fn right(&self) -> f32
+    {
+
+        return self.body.right();
+
+    }
+
+    //Sign of the vertical direction gravity is currently pulling the body in
+    pub fn down(&self) -> f32
+    {
+
+        return self.gravity.down;
+
+    }
+
+    pub fn flipped(&self) -> bool
+    {
+
+        return self.gravity.down < 0.0;
+
+    }
 
 }
 
-fn schedule_request_systems(schedule: &mut Builder)
+//Which part of a body stays put when it changes size. Bottom and top keep the left edge where it is, as resizing always has,
+//while bottom centre keeps the body centred over the same spot
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+pub enum Anchor
 {
 
-	schedule.add_system(resize_failure_system());
-	schedule.add_system(resize_success_system());
+    Bottom,
+    BottomCentre,
+    Top,
+    Centre,
+    Left, //Left and right also keep the bottom in place, since most bodies are stood on something
+    Right
 
 }
 
+impl Anchor
+{
 
+    fn resize(&self, rect: &Rect, width: f32, height: f32) -> Rect
+    {
+
+        let centre_x = rect.x + (rect.width - width) * 0.5;
+        let centre_y = rect.y + (rect.height - height) * 0.5;
+        let bottom = rect.bottom() - height;
+
+        match self
+        {
+
+            Anchor::Bottom => return Rect { x: rect.x, y: bottom, width, height },
+            Anchor::BottomCentre => return Rect { x: centre_x, y: bottom, width, height },
+            Anchor::Top => return Rect { x: rect.x, y: rect.y, width, height },
+            Anchor::Centre => return Rect { x: centre_x, y: centre_y, width, height },
+            Anchor::Left => return Rect { x: rect.x, y: bottom, width, height },
+            Anchor::Right => return Rect { x: rect.right() - width, y: bottom, width, height }
+
+        }
+
+    }
+
+}
+
+//Asks the physics to resize a body if there is room for it. Bodies which would not fit can be nudged up to the given distance,
+//trying the nearest positions first and sideways before up or down
+pub struct RequestSizeChange
+{
+
+	pub width: f32,
+	pub height: f32,
+	pub anchor: Anchor,
+	pub nudge: f32
+
+}
+
+impl RequestSizeChange
+{
+
+    pub fn new(width: f32, height: f32) -> RequestSizeChange
+    {
+
+        return RequestSizeChange { width, height, anchor: Anchor::Bottom, nudge: 0.0 };
+
+    }
+
+    pub fn with_anchor(mut self, anchor: Anchor) -> RequestSizeChange
+    {
+
+        self.anchor = anchor;
+
+        return self;
+
+    }
+
+    pub fn with_nudge(mut self, nudge: f32) -> RequestSizeChange
+    {
+
+        self.nudge = nudge;
+
+        return self;
+
+    }
+
+    fn offsets(&self) -> Vec<(f32, f32)>
+    {
+
+        let mut offsets = vec![(0.0, 0.0)];
+        let mut distance = 0.0;
+
+        while distance < self.nudge
+        {
+
+            distance = (distance + NUDGE_STEP).min(self.nudge);
+
+            offsets.extend([(-distance, 0.0), (distance, 0.0), (0.0, -distance), (0.0, distance)]);
+
+        }
+
+        return offsets;
+
+    }
+
+}
+
+//How finely nudging searches for free space, in pixels
+const NUDGE_STEP: f32 = 1.0;
+
+pub struct RequestSizeChangeSuccess {}
+pub struct RequestSizeChangeFailure {}
+
+pub struct Gravity
+{
+
+    pub force: f32,
+    pub max: f32
+
+}
+
+pub struct HasGravity {}
+
+//Scales the global gravity and maximum fall speed for a single body
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct GravityScale
+{
+
+    pub scale: f32,
+    pub max: f32
+
+}
+
+//Volume which changes gravity for bodies whose centre is inside it, scaling its strength and optionally flipping it upwards
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct GravityZone
+{
+
+    pub rect: Rect,
+    pub strength: f32,
+    pub flip: bool
+
+}
+
+impl GravityZone
+{
+
+    pub fn contains(&self, body: &Rect) -> bool
+    {
+
+        let centre = Point { x: body.x + body.width * 0.5, y: body.y + body.height * 0.5 };
+
+        return centre.x >= self.rect.x && centre.x < self.rect.right() && centre.y >= self.rect.y && centre.y < self.rect.bottom();
+
+    }
+
+}
+
+//The gravity a single body actually feels, once its own scale and any zone it is in are taken into account. Force and max
+//are per step like the global gravity, and down is the sign of the direction it pulls in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BodyGravity
+{
+
+    pub force: f32,
+    pub max: f32,
+    pub down: f32
+
+}
+
+impl BodyGravity
+{
+
+    pub fn new(gravity: &Gravity, scale: Option<&GravityScale>, zone: Option<&GravityZone>) -> BodyGravity
+    {
+
+        let (force, max) = scale.map_or((gravity.force, gravity.max), |scale| { return (gravity.force * scale.scale, gravity.max * scale.max); });
+
+        return BodyGravity
+        {
+
+            force: force * zone.map_or(1.0, |zone| { return zone.strength; }),
+            max,
+            down: if zone.is_some_and(|zone| { return zone.flip; }) { -1.0 } else { 1.0 }
+
+        };
+
+    }
+
+    //Adds one step of gravity to a vertical velocity, without letting the fall go past the maximum speed
+    pub fn apply(&self, velocity: f32) -> f32
+    {
+
+        return self.down * (velocity * self.down + self.force).min(self.max);
+
+    }
+
+}
+
+impl Default for BodyGravity
+{
+
+    fn default() -> BodyGravity
+    {
+
+        return BodyGravity { force: 0.0, max: 0.0, down: 1.0 };
+
+    }
+
+}
+
+//Volume of water or similar which pushes bodies inside it upwards and slows them down. Buoyancy is the upward push when fully
+//submerged as a fraction of gravity, so above 1 floats, drag is the fraction of velocity lost per second, max_fall caps
+//sinking speed in pixels per second, and clarity is how much of a submerged body can still be seen, from 0 to 1
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct Fluid
+{
+
+    pub rect: Rect,
+    pub buoyancy: f32,
+    pub drag: f32,
+    pub max_fall: f32,
+    pub clarity: f32
+
+}
+
+impl Fluid
+{
+
+    //Fraction of the body's height below the surface
+    pub fn depth(&self, body: &Rect) -> f32
+    {
+
+        if !Rect::intersects(&self.rect, body)
+        {
+
+            return 0.0;
+
+        }
+
+        let top = body.y.max(self.rect.y);
+        let bottom = body.bottom().min(self.rect.bottom());
+
+        return ((bottom - top) / body.height).clamp(0.0, 1.0);
+
+    }
+
+}
+
+//Added to dynamic bodies while they are inside a fluid
+pub struct Submerged
+{
+
+    pub fluid: Entity,
+    pub depth: f32,
+    pub clarity: f32
+
+}
+
+//Marks walls which are too slick to kick off
+pub struct NoWallJump {}
+
+//Region which bodies can climb up and down through, such as a ladder
+pub struct Climbable
+{
+
+    pub rect: Rect
+
+}
+
+//Dynamic bodies which collide with each other. Pushable bodies are shoved aside by others, sharing the push by mass when both
+//are pushable, while bodies which are not pushable can only be stood on or blocked
+pub struct Solid
+{
+
+    pub mass: f32,
+    pub pushable: bool
+
+}
+
+//Which side of a dynamic body is touching something
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Side
+{
+
+    Top,
+    Bottom,
+    Left,
+    Right
+
+}
+
+impl Side
+{
+
+    //Side of the body touched by a surface pushing it along the normal
+    pub fn from_normal(normal: Point) -> Side
+    {
+
+        if -normal.y >= WALKABLE_NORMAL
+        {
+
+            return Side::Bottom;
+
+        }
+
+        if normal.y >= WALKABLE_NORMAL
+        {
+
+            return Side::Top;
+
+        }
+
+        return if normal.x > 0.0 { Side::Left } else { Side::Right };
+
+    }
+
+    //Side of the body resting flush against a rect, if any
+    pub fn touching(body: &Rect, rect: &Rect) -> Option<Side>
+    {
+
+        let across = body.x < rect.right() - FLOATING_POINT_ERROR && body.right() > rect.x + FLOATING_POINT_ERROR;
+        let along = body.y < rect.bottom() - FLOATING_POINT_ERROR && body.bottom() > rect.y + FLOATING_POINT_ERROR;
+
+        if across && (body.bottom() - rect.y).abs() <= CONTACT_DISTANCE
+        {
+
+            return Some(Side::Bottom);
+
+        }
+
+        if across && (body.y - rect.bottom()).abs() <= CONTACT_DISTANCE
+        {
+
+            return Some(Side::Top);
+
+        }
+
+        if along && (body.x - rect.right()).abs() <= CONTACT_DISTANCE
+        {
+
+            return Some(Side::Left);
+
+        }
+
+        if along && (body.right() - rect.x).abs() <= CONTACT_DISTANCE
+        {
+
+            return Some(Side::Right);
+
+        }
+
+        return None;
+
+    }
+
+    fn normal(&self) -> Point
+    {
+
+        match self
+        {
+
+            Side::Top => return Point { x: 0.0, y: 1.0 },
+            Side::Bottom => return Point { x: 0.0, y: -1.0 },
+            Side::Left => return Point { x: 1.0, y: 0.0 },
+            Side::Right => return Point { x: -1.0, y: 0.0 }
+
+        }
+
+    }
+
+}
+
+//How close a body has to be to a surface to count as touching it
+const CONTACT_DISTANCE: f32 = 0.01;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Contact
+{
+
+    pub other: Entity,
+    pub side: Side,
+    pub normal: Point //Direction the other body pushes this one
+
+}
+
+//Everything a dynamic body touched this step, summarised by side. Static and one way bodies are found by looking for
+//surfaces the body rests flush against, polygons and solid bodies are recorded by their collision systems as they resolve
+pub struct Contacts
+{
+
+    pub grounded: bool,
+    pub wall_left: bool,
+    pub wall_right: bool,
+    pub ceiling: bool,
+    pub touching: Vec<Contact>,
+    recorded: Vec<Contact>,
+    floor: Side //The side gravity currently pulls the body towards
+
+}
+
+impl Contacts
+{
+
+    pub fn new() -> Contacts
+    {
+
+        return Contacts { grounded: false, wall_left: false, wall_right: false, ceiling: false, touching: Vec::new(), recorded: Vec::new(), floor: Side::Bottom };
+
+    }
+
+    fn record(&mut self, other: Entity, normal: Point)
+    {
+
+        self.recorded.push(Contact { other, side: Side::from_normal(normal), normal });
+
+    }
+
+    pub fn touches(&self, other: Entity) -> bool
+    {
+
+        return self.touching.iter().any(|contact| { return contact.other == other; });
+
+    }
+
+    //Whatever the body is standing on, if anything
+    pub fn ground(&self) -> Option<Entity>
+    {
+
+        return self.on(self.floor);
+
+    }
+
+    pub fn on(&self, side: Side) -> Option<Entity>
+    {
+
+        return self.touching.iter().find(|contact| { return contact.side == side; }).map(|contact| { return contact.other; });
+
+    }
+
+}
+
+impl Default for Contacts
+{
+
+    fn default() -> Contacts
+    {
+
+        return Contacts::new();
+
+    }
+
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ContactPhase
+{
+
+    Begin,
+    End
+
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ContactEvent
+{
+
+    pub entity: Entity,
+    pub contact: Contact,
+    pub phase: ContactPhase
+
+}
+
+//Contacts which started or ended during the last step, for every body with a Contacts component
+pub struct ContactEvents
+{
+
+    pub events: Vec<ContactEvent>
+
+}
+
+impl ContactEvents
+{
+
+    pub fn new() -> ContactEvents
+    {
+
+        return ContactEvents { events: Vec::new() };
+
+    }
+
+}
+
+impl Default for ContactEvents
+{
+
+    fn default() -> ContactEvents
+    {
+
+        return ContactEvents::new();
+
+    }
+
+}
+
+//Size of the broadphase grid cells, in pixels
+const BROADPHASE_CELL: f32 = 128.0;
+
+//Copies of every static and one way rect, sorted into grids so that collision only has to look at nearby bodies. Bodies which
+//never move are only re-sorted when some are added or removed, moving platforms are refreshed every step
+#[derive(Clone, Copy)]
+pub struct BroadphaseEntry
+{
+
+    pub entity: Entity,
+    pub change: (f32, f32), //How far the body moved this step
+    pub layers: Layers
+
+}
+
+pub struct Broadphase
+{
+
+    statics: SpatialGrid<BroadphaseEntry>,
+    oneways: SpatialGrid<BroadphaseEntry>,
+    polygons: SpatialGrid<BroadphaseEntry>, //By their bounds
+    moving_statics: Vec<(BroadphaseEntry, Rect)>,
+    moving_oneways: Vec<(BroadphaseEntry, Rect)>,
+    dirty: bool
+
+}
+
+impl Broadphase
+{
+
+    pub fn new() -> Broadphase
+    {
+
+        return Broadphase { statics: SpatialGrid::new(BROADPHASE_CELL), oneways: SpatialGrid::new(BROADPHASE_CELL), polygons: SpatialGrid::new(BROADPHASE_CELL), moving_statics: Vec::new(), moving_oneways: Vec::new(), dirty: true };
+
+    }
+
+    //Forces the grids of bodies which never move to be rebuilt on the next step. Needed whenever one is added, removed, resized or
+    //moved, such as when a level is loaded
+    pub fn mark_dirty(&mut self)
+    {
+
+        self.dirty = true;
+
+    }
+
+    //Every static body, moving or not, whose rect overlaps or touches the area and which collides with the given layers
+    pub fn statics(&self, area: &Rect, layers: &Layers) -> Vec<(BroadphaseEntry, Rect)>
+    {
+
+        return Broadphase::gather(&self.statics, &self.moving_statics, area, layers);
+
+    }
+
+    pub fn oneways(&self, area: &Rect, layers: &Layers) -> Vec<(BroadphaseEntry, Rect)>
+    {
+
+        return Broadphase::gather(&self.oneways, &self.moving_oneways, area, layers);
+
+    }
+
+    //Every polygon whose bounds overlap or touch the area and which collides with the given layers
+    pub fn polygons(&self, area: &Rect, layers: &Layers) -> Vec<(BroadphaseEntry, Rect)>
+    {
+
+        return Broadphase::gather(&self.polygons, &[], area, layers);
+
+    }
+
+    fn gather(grid: &SpatialGrid<BroadphaseEntry>, moving: &[(BroadphaseEntry, Rect)], area: &Rect, layers: &Layers) -> Vec<(BroadphaseEntry, Rect)>
+    {
+
+        let mut found: Vec<(BroadphaseEntry, Rect)> = grid.query(area).into_iter().copied().collect();
+
+        //Moving bodies are swept relative to their own movement, so look as far as they moved
+        found.extend(moving.iter().filter(|(entry, rect)|
+        {
+
+            let margin = entry.change.0.abs().max(entry.change.1.abs()) + FLOATING_POINT_ERROR;
+
+            return Rect::intersects(&rect.expand(margin), area);
+
+        }).copied());
+
+        found.retain(|(entry, _)| { return Layers::interact(&entry.layers, layers); });
+
+        return found;
+
+    }
+
+}
+
+impl Default for Broadphase
+{
+
+    fn default() -> Broadphase
+    {
+
+        return Broadphase::new();
+
+    }
+
+}
+
+#[system(for_each)]
+fn reset_temp_velocity(dynamic: &mut DynamicBody)
+{
+
+	dynamic.temp_velocity = Velocity::new(0.0, 0.0);
+
+}
+
+#[system(for_each)]
+fn kinematic_toggle(kinematic: &mut Kinematic, _activate: &Activate, cmd: &mut CommandBuffer, entity: &Entity)
+{
+
+    //Other motions only use activation to get going, and one shot bodies only ever go once
+    if kinematic.motion != Motion::Toggle
+    {
+
+        if kinematic.direction == 0 && (kinematic.motion != Motion::OneShot || kinematic.time < kinematic.max)
+        {
+
+            kinematic.start(1);
+
+        }
+
+    }
+    else if kinematic.time >= kinematic.max
+    {
+
+        kinematic.start(-1);
+
+    }
+    else if kinematic.time <= 0
+    {
+
+        kinematic.start(1);
+
+    }
+
+    cmd.remove_component::<Activate>(*entity);
+
+}
+
+#[system(for_each)]
+fn kinematic_stop(kinematic: &mut Kinematic, watcher: &Watcher)
+{
+
+    if kinematic.motion == Motion::Toggle && !watcher.activated
+    {
+
+        kinematic.stop();
+
+    }
+
+}
+
+#[system(for_each)]
+#[filter(!component::<Activate>())]
+fn kinematic_consume_stop(kinematic: &mut Kinematic, _watcher: &ConsumeWatcher)
+{
+
+    if kinematic.motion == Motion::Toggle && (kinematic.time <= 0 || kinematic.time >= kinematic.max)
+    {
+
+        kinematic.stop();
+
+    }
+
+}
+
+#[system]
+#[write_component(Kinematic)]
+#[write_component(StaticBody)]
+#[write_component(DynamicBody)]
+#[read_component(Solid)]
+#[read_component(PolygonBody)]
+#[read_component(Layers)]
+fn kinematic_static_move(world: &mut SubWorld, cmd: &mut CommandBuffer, #[resource] broadphase: &Broadphase, #[resource] time: &Timestep)
+{
+
+    let mut kinematic_query = <(Entity, &Kinematic, Option<&Layers>)>::query().filter(component::<StaticBody>());
+    let kinematics: Vec<(Entity, Crush, Layers)> = kinematic_query.iter(world).map(|(entity, kinematic, layers)| { return (*entity, kinematic.crush, Layers::or_default(layers)); }).collect();
+
+    for (entity, crush, layers) in kinematics
+    {
+
+        //Gathered again before each kinematic moves, so that those which have already moved this step are where they are now
+        let moving = if crush == Crush::Push { Vec::new() } else { Blockers::moving(world) };
+
+        let mut query = <(Entity, &mut DynamicBody, Option<&Layers>)>::query();
+
+        let (mut platform_world, mut rest) = world.split::<(&mut Kinematic, &mut StaticBody)>();
+        let (mut body_world, blocker_world) = rest.split_for_query(&query);
+
+        let (kinematic, static_body) = match <(&mut Kinematic, &mut StaticBody)>::query().get_mut(&mut platform_world, entity)
+        {
+
+            Ok(platform) => platform,
+            Err(_) => continue
+
+        };
+
+        let state = kinematic.save();
+        let new_pos = kinematic.update(time.step);
+
+        let velocity = (new_pos.0 - static_body.body.x, new_pos.1 - static_body.body.y);
+        kinematic.change = Velocity { x: velocity.0, y: velocity.1 };
+
+        let mut moved = static_body.body;
+        moved.x = new_pos.0;
+        moved.y = new_pos.1;
+
+        let mut pushes: Vec<KinematicPush> = Vec::new();
+
+        for (other, body, body_layers) in query.iter_mut(&mut body_world).filter(|(_, _, l)| { return Layers::interact(&layers, &Layers::or_default(*l)); })
+        {
+
+            //Move along with the platform as necessary
+            if Kinematic::should_move(&static_body.body, &mut body.body)
+            {
+
+                pushes.push((*other, body, velocity, true, Layers::or_default(body_layers)));
+
+            }
+            else
+            {
+
+                let correction = Rect::collides(&body.body, &moved, (-velocity.0, -velocity.1));
+
+                pushes.push((*other, body, correction, false, Layers::or_default(body_layers)));
+
+            }
+
+        }
+
+        let blockers = if crush == Crush::Push { None } else { Some(Blockers::around(&pushes, &moving, broadphase, &blocker_world)) };
+
+        if kinematic.squeeze(pushes, state, entity, cmd, blockers)
+        {
+
+            static_body.body = moved;
+
+        }
+
+    }
+
+}
+
+#[system]
+#[write_component(Kinematic)]
+#[write_component(OneWayBody)]
+#[write_component(DynamicBody)]
+#[read_component(InteractsWithOneWay)]
+#[read_component(StaticBody)]
+#[read_component(Solid)]
+#[read_component(PolygonBody)]
+#[read_component(Layers)]
+fn kinematic_oneway_move(world: &mut SubWorld, cmd: &mut CommandBuffer, #[resource] broadphase: &Broadphase, #[resource] time: &Timestep)
+{
+
+    let mut kinematic_query = <(Entity, &Kinematic, Option<&Layers>)>::query().filter(component::<OneWayBody>());
+    let kinematics: Vec<(Entity, Crush, Layers)> = kinematic_query.iter(world).map(|(entity, kinematic, layers)| { return (*entity, kinematic.crush, Layers::or_default(layers)); }).collect();
+
+    for (entity, crush, layers) in kinematics
+    {
+
+        //Gathered again before each kinematic moves, so that those which have already moved this step are where they are now
+        let moving = if crush == Crush::Push { Vec::new() } else { Blockers::moving(world) };
+
+        let mut query = <(Entity, &mut DynamicBody, &InteractsWithOneWay, Option<&Layers>)>::query();
+
+        let (mut platform_world, mut rest) = world.split::<(&mut Kinematic, &mut OneWayBody)>();
+        let (mut body_world, blocker_world) = rest.split_for_query(&query);
+
+        let (kinematic, oneway_body) = match <(&mut Kinematic, &mut OneWayBody)>::query().get_mut(&mut platform_world, entity)
+        {
+
+            Ok(platform) => platform,
+            Err(_) => continue
+
+        };
+
+        let state = kinematic.save();
+        let new_pos = kinematic.update(time.step);
+
+        let velocity = (new_pos.0 - oneway_body.body.x, new_pos.1 - oneway_body.body.y);
+        kinematic.change = Velocity { x: velocity.0, y: velocity.1 };
+
+        let mut moved = oneway_body.body;
+        moved.x = new_pos.0;
+        moved.y = new_pos.1;
+
+        let mut pushes: Vec<KinematicPush> = Vec::new();
+
+        for (other, body, _, body_layers) in query.iter_mut(&mut body_world).filter(|(_, _, _, l)| { return Layers::interact(&layers, &Layers::or_default(*l)); })
+        {
+
+            //Move along with the platform as necessary
+            let flipped = body.flipped();
+
+            if Kinematic::should_move_oneway(&oneway_body.body, &mut body.body, flipped)
+            {
+
+                pushes.push((*other, body, velocity, true, Layers::or_default(body_layers)));
+
+            }
+            else
+            {
+
+                let correction = Rect::collides(&body.body, &moved, (-velocity.0, -velocity.1));
+
+                let landing = if flipped { correction.1 > 0.0 && body.body.y + velocity.1 >= moved.bottom() } else { correction.1 < 0.0 && body.body.bottom() + velocity.1 <= moved.y };
+
+                if landing
+                {
+
+                    pushes.push((*other, body, (0.0, correction.1), false, Layers::or_default(body_layers)));
+
+                }
+
+            }
+
+        }
+
+        let blockers = if crush == Crush::Push { None } else { Some(Blockers::around(&pushes, &moving, broadphase, &blocker_world)) };
+
+        if kinematic.squeeze(pushes, state, entity, cmd, blockers)
+        {
+
+            oneway_body.body = moved;
+
+        }
+
+    }
+
+}
+
+//Bodies which never move are only sorted into the grids when the broadphase is marked dirty, so anything which adds, removes,
+//resizes or moves them must mark it. Runs before anything reads the broadphase, so the first step sees the level
+#[system]
+#[read_component(StaticBody)]
+#[read_component(OneWayBody)]
+#[read_component(Kinematic)]
+#[read_component(PolygonBody)]
+#[read_component(Layers)]
+fn update_broadphase(world: &mut SubWorld, #[resource] broadphase: &mut Broadphase)
+{
+
+    if broadphase.dirty
+    {
+
+        broadphase.statics.clear();
+        broadphase.oneways.clear();
+        broadphase.polygons.clear();
+
+        let mut static_query = <(Entity, &StaticBody, Option<&Layers>)>::query().filter(!component::<Kinematic>());
+
+        for (entity, body, layers) in static_query.iter(world)
+        {
+
+            broadphase.statics.insert(BroadphaseEntry { entity: *entity, change: (0.0, 0.0), layers: Layers::or_default(layers) }, body.body);
+
+        }
+
+        let mut oneway_query = <(Entity, &OneWayBody, Option<&Layers>)>::query().filter(!component::<Kinematic>());
+
+        for (entity, body, layers) in oneway_query.iter(world)
+        {
+
+            broadphase.oneways.insert(BroadphaseEntry { entity: *entity, change: (0.0, 0.0), layers: Layers::or_default(layers) }, body.body);
+
+        }
+
+        let mut polygon_query = <(Entity, &PolygonBody, Option<&Layers>)>::query();
+
+        for (entity, polygon, layers) in polygon_query.iter(world)
+        {
+
+            broadphase.polygons.insert(BroadphaseEntry { entity: *entity, change: (0.0, 0.0), layers: Layers::or_default(layers) }, polygon.bounds);
+
+        }
+
+        broadphase.dirty = false;
+
+    }
+
+    update_moving(world, broadphase);
+
+}
+
+//Moving bodies are gathered again once the kinematic systems have moved them, so collisions see where they are now
+#[system]
+#[read_component(StaticBody)]
+#[read_component(OneWayBody)]
+#[read_component(Kinematic)]
+#[read_component(Layers)]
+fn update_moving_broadphase(world: &mut SubWorld, #[resource] broadphase: &mut Broadphase)
+{
+
+    update_moving(world, broadphase);
+
+}
+
+fn update_moving(world: &SubWorld, broadphase: &mut Broadphase)
+{
+
+    let mut moving_static_query = <(Entity, &StaticBody, &Kinematic, Option<&Layers>)>::query();
+
+    broadphase.moving_statics.clear();
+    broadphase.moving_statics.extend(moving_static_query.iter(world).map(|(entity, body, kinematic, layers)| { return (BroadphaseEntry { entity: *entity, change: (kinematic.change.x, kinematic.change.y), layers: Layers::or_default(layers) }, body.body); }));
+
+    let mut moving_oneway_query = <(Entity, &OneWayBody, &Kinematic, Option<&Layers>)>::query();
+
+    broadphase.moving_oneways.clear();
+    broadphase.moving_oneways.extend(moving_oneway_query.iter(world).map(|(entity, body, kinematic, layers)| { return (BroadphaseEntry { entity: *entity, change: (kinematic.change.x, kinematic.change.y), layers: Layers::or_default(layers) }, body.body); }));
+
+}
+
+#[system(for_each)]
+fn velocity(dynamic_body: &mut DynamicBody, velocity: &Velocity)
+{
+
+    dynamic_body.body.translate((velocity.x, velocity.y));
+
+}
+
+#[system(for_each)]
+fn facing(dynamic_body: &mut DynamicBody, velocity: &Velocity)
+{
+
+    if velocity.x < -FLOATING_POINT_ERROR
+    {
+
+        dynamic_body.left = true;
+
+    }
+    else if velocity.x > FLOATING_POINT_ERROR
+    {
+
+        dynamic_body.left = false;
+
+    }
+
+}
+
+#[system(for_each)]
+#[read_component(GravityZone)]
+fn gravity(velocity: &mut Velocity, _g: &HasGravity, dynamic_body: Option<&mut DynamicBody>, scale: Option<&GravityScale>, world: &mut SubWorld, #[resource] gravity: &Gravity)
+{
+
+    match dynamic_body
+    {
+
+        Some(dynamic_body) =>
+        {
+
+            let mut zone_query = <&GravityZone>::query();
+            let zone = zone_query.iter(world).find(|zone| { return zone.contains(&dynamic_body.body); });
+
+            dynamic_body.gravity = BodyGravity::new(gravity, scale, zone);
+
+            velocity.y = dynamic_body.gravity.apply(velocity.y);
+
+        },
+        None => velocity.y = BodyGravity::new(gravity, scale, None).apply(velocity.y)
+
+    }
+
+}
+
+//Bodies take on the fluid they are deepest in, which offsets gravity by its buoyancy and drags on them in proportion to depth
+#[system(for_each)]
+#[read_component(Fluid)]
+fn fluid(dynamic_body: &DynamicBody, velocity: &mut Velocity, has_gravity: Option<&HasGravity>, submerged: Option<&mut Submerged>, world: &mut SubWorld, cmd: &mut CommandBuffer, entity: &Entity, #[resource] time: &Timestep)
+{
+
+    let mut fluid_query = <(Entity, &Fluid)>::query();
+
+    let deepest = fluid_query.iter(world)
+        .map(|(fluid_entity, fluid)| { return (*fluid_entity, fluid, fluid.depth(&dynamic_body.body)); })
+        .filter(|(_, _, depth)| { return *depth > 0.0; })
+        .max_by(|a, b| { return a.2.total_cmp(&b.2); });
+
+    let (fluid_entity, fluid, depth) = match deepest
+    {
+
+        Some(deepest) => deepest,
+        None =>
+        {
+
+            if submerged.is_some()
+            {
+
+                cmd.remove_component::<Submerged>(*entity);
+
+            }
+
+            return;
+
+        }
+
+    };
+
+    let seconds = time.step as f32 / 1000.0;
+
+    //Gravity has already been worked out for this body this step, so buoyancy pushes against whatever pulls it down here
+    let down = dynamic_body.down();
+
+    if has_gravity.is_some()
+    {
+
+        velocity.y -= down * dynamic_body.gravity.force * fluid.buoyancy * depth;
+
+    }
+
+    let drag = (1.0 - fluid.drag * depth * seconds).max(0.0);
+
+    velocity.x *= drag;
+    velocity.y *= drag;
+    velocity.y = (velocity.y * down).min(fluid.max_fall * seconds) * down;
+
+    match submerged
+    {
+
+        Some(submerged) =>
+        {
+
+            submerged.fluid = fluid_entity;
+            submerged.depth = depth;
+            submerged.clarity = fluid.clarity;
+
+        },
+        None => cmd.add_component(*entity, Submerged { fluid: fluid_entity, depth, clarity: fluid.clarity })
+
+    }
+
+}
+
+#[system(for_each)]
+fn reset_oneway(reset: &mut ResetOneWayInteraction, cmd: &mut CommandBuffer, entity: &Entity, #[resource] time: &Timestep)
+{
+
+    reset.time += time.step;
+
+    if reset.time >= reset.duration
+    {
+
+		cmd.remove_component::<ResetOneWayInteraction>(*entity);
+		cmd.add_component(*entity, InteractsWithOneWay {});
+
+    }
+
+}
+
+#[system(for_each)]
+fn top_collision(body: &mut DynamicBody, #[resource] time: &Timestep)
+{
+
+    body.top_collision += time.step;
+
+}
+
+//Snap the body flush against the face of the rect it was swept into
+fn snap_to_face(body: &mut Rect, rect: &Rect, (nx, ny): (f32, f32))
+{
+
+    if ny < 0.0
+    {
+
+        body.y = rect.y - body.height;
+
+    }
+    else if ny > 0.0
+    {
+
+        body.y = rect.bottom();
+
+    }
+    else if nx < 0.0
+    {
+
+        body.x = rect.x - body.width;
+
+    }
+    else
+    {
+
+        body.x = rect.right();
+
+    }
+
+}
+
+//Sweep the move the body made this step against each rect, relative to that rect's own movement, so that fast bodies cannot pass
+//through thin geometry. Returns the earliest hit on an axis which has not already been resolved
+fn earliest_hit(body: &Rect, displacement: (f32, f32), rects: &[(BroadphaseEntry, Rect)], resolved: (bool, bool)) -> Option<(Rect, (f32, f32))>
+{
+
+    let mut earliest: Option<(f32, Rect, (f32, f32))> = None;
+
+    for (entry, rect) in rects
+    {
+
+        let change = entry.change;
+        let relative = (displacement.0 - change.0, displacement.1 - change.1);
+
+        let mut start = *body;
+        start.translate((-relative.0, -relative.1));
+
+        if let Some((t, normal)) = Rect::sweep(&start, rect, relative)
+        {
+
+            if (normal.0 != 0.0 && resolved.0) || (normal.1 != 0.0 && resolved.1)
+            {
+
+                continue;
+
+            }
+
+            if earliest.is_none_or(|(first, _, _)| { return t < first; })
+            {
+
+                earliest = Some((t, *rect, normal));
+
+            }
+
+        }
+
+    }
+
+    return earliest.map(|(_, rect, normal)| { return (rect, normal); });
+
+}
+
+//Area around a move in which bodies might be hit or need to push the body back out. Pushes can move the body up to its own size
+fn collision_area(body: &Rect, displacement: (f32, f32)) -> Rect
+{
+
+    let mut start = *body;
+    start.translate((-displacement.0, -displacement.1));
+
+    return Rect::union(&start, body).expand(body.width.max(body.height));
+
+}
+
+#[system(for_each)]
+fn static_collision(dynamic_body: &mut DynamicBody, velocity: &mut Velocity, layers: Option<&Layers>, #[resource] broadphase: &Broadphase)
+{
+
+    let mut top = false;
+    let down = dynamic_body.down();
+
+    let mut displacement = (velocity.x + dynamic_body.temp_velocity.x, velocity.y + dynamic_body.temp_velocity.y);
+    let mut resolved = (false, false);
+
+    let nearby = broadphase.statics(&collision_area(&dynamic_body.body, displacement), &Layers::or_default(layers));
+
+    //At most one hit per axis, since a resolved axis is no longer moving
+    for _ in 0..2
+    {
+
+        let hit = earliest_hit(&dynamic_body.body, displacement, &nearby, resolved);
+
+        let (rect, normal) = match hit
+        {
+
+            Some(hit) => hit,
+            None => break
+
+        };
+
+        snap_to_face(&mut dynamic_body.body, &rect, normal);
+
+        if normal.1 != 0.0
+        {
+
+            velocity.y = 0.0;
+            displacement.1 = 0.0;
+            resolved.1 = true;
+
+            top = top || normal.1 * down < 0.0;
+
+        }
+        else
+        {
+
+            velocity.x = 0.0;
+            displacement.0 = 0.0;
+            resolved.0 = true;
+
+        }
+
+    }
+
+	let mut collision_velocity = (velocity.x + dynamic_body.temp_velocity.x, velocity.y + dynamic_body.temp_velocity.y);
+
+    //Anything still overlapping was pushed into place rather than moving there, for example by a moving platform. As with the
+    //sweep, moving bodies are corrected against relative to their own movement, so a platform carrying the body does not count
+    //the ride it gave as the body moving into it
+    for (entry, rect) in nearby.iter()
+    {
+
+        let correction = Rect::collides(&dynamic_body.body, rect, (collision_velocity.0 - entry.change.0, collision_velocity.1 - entry.change.1));
+
+        dynamic_body.body.translate(correction);
+        velocity.add(correction);
+		collision_velocity.0 += correction.0;
+		collision_velocity.1 += correction.1;
+
+        if correction.1 != 0.0
+        {
+
+            velocity.y = 0.0;
+			collision_velocity.1 = 0.0;
+
+        }
+
+        if correction.1 * down < 0.0
+        {
+
+            top = true;
+
+        }
+
+    }
+
+    if top
+    {
+
+        dynamic_body.top_collision = 0;
+
+    }
+
+}
+
+//Floors are resolved straight upwards, so that standing on a slope does not slide the body down it. Bodies which were on
+//the ground last step are snapped down onto slopes beneath them, so that walking downhill does not turn into a series of hops
+#[system(for_each)]
+#[read_component(PolygonBody)]
+#[read_component(Layers)]
+fn polygon_collision(dynamic_body: &mut DynamicBody, velocity: &mut Velocity, layers: Option<&Layers>, interacts: Option<&InteractsWithOneWay>, mut contacts: Option<&mut Contacts>, world: &mut SubWorld, #[resource] broadphase: &Broadphase, #[resource] time: &Timestep)
+{
+
+    let layers = Layers::or_default(layers);
+
+    //Pushing the body out of a polygon moves it no further than its own size, and it is snapped down no further than its speed allows
+    let reach = dynamic_body.body.width.max(dynamic_body.body.height) + (velocity.x.abs() + velocity.y.abs()) / WALKABLE_NORMAL + 1.0;
+
+    let polygons: Vec<(Entity, &PolygonBody)> = broadphase.polygons(&dynamic_body.body.expand(reach), &layers).into_iter()
+        .filter_map(|(entry, _)| { return world.entry_ref(entry.entity).ok()?.into_component::<PolygonBody>().ok().map(|polygon| { return (entry.entity, polygon); }); })
+        .filter(|(_, p)| { return !p.oneway || interacts.is_some(); })
+        .collect();
+
+    let mut top = false;
+    let down = dynamic_body.down();
+
+    //Where the body was before it moved this step
+    let previous = Rect { x: dynamic_body.body.x - velocity.x, y: dynamic_body.body.y - velocity.y, ..dynamic_body.body };
+
+    for (entity, polygon) in polygons.iter()
+    {
+
+        let mtv = match polygon.overlap(&dynamic_body.body)
+        {
+
+            Some(mtv) => mtv,
+            None => continue
+
+        };
+
+        let length = mtv.dot(mtv).sqrt();
+
+        if length < FLOATING_POINT_ERROR
+        {
+
+            continue;
+
+        }
+
+        let normal = mtv.scale(1.0 / length);
+        let floor = -normal.y * down;
+
+        //One way polygons only catch bodies landing on their floors which were not already inside them, so bodies jumping up
+        //through them pass straight on
+        if polygon.oneway && (floor < WALKABLE_NORMAL || polygon.overlap(&previous).is_some_and(|old| { return old.dot(old).sqrt() > CONTACT_DISTANCE; }))
+        {
+
+            continue;
+
+        }
+
+        if let Some(contacts) = contacts.as_mut()
+        {
+
+            contacts.record(*entity, normal);
+
+        }
+
+        if floor >= WALKABLE_NORMAL
+        {
+
+            dynamic_body.body.y -= length / -normal.y;
+            velocity.y = down * (velocity.y * down).min(0.0);
+
+            top = true;
+
+        }
+        else
+        {
+
+            dynamic_body.body.translate((mtv.x, mtv.y));
+
+            //Only remove the part of the velocity heading into the surface
+            let into = velocity.x * normal.x + velocity.y * normal.y;
+
+            if into < 0.0
+            {
+
+                velocity.x -= normal.x * into;
+                velocity.y -= normal.y * into;
+
+            }
+
+        }
+
+    }
+
+    let grounded = dynamic_body.top_collision <= time.step;
+
+    if !top && grounded && velocity.y * down >= 0.0
+    {
+
+        let snap = velocity.x.abs() / WALKABLE_NORMAL + 1.0;
+
+        let mut probe = dynamic_body.body;
+        probe.y += snap * down;
+
+        let mut landed: Option<(Entity, Point)> = None;
+
+        for (entity, polygon) in polygons.iter()
+        {
+
+            if let Some(mtv) = polygon.overlap(&probe)
+            {
+
+                let length = mtv.dot(mtv).sqrt();
+
+                if length >= FLOATING_POINT_ERROR && -mtv.y * down / length >= WALKABLE_NORMAL
+                {
+
+                    probe.y -= length * length / -mtv.y;
+                    landed = Some((*entity, mtv.scale(1.0 / length)));
+
+                }
+
+            }
+
+        }
+
+        let blocked = broadphase.statics(&probe, &layers).iter().any(|(_, rect)| { return Rect::intersects(&probe, rect); });
+
+        if let Some((entity, normal)) = landed.filter(|_| { return !blocked && (probe.y - dynamic_body.body.y) * down > 0.0; })
+        {
+
+            dynamic_body.body = probe;
+            velocity.y = 0.0;
+
+            top = true;
+
+            if let Some(contacts) = contacts.as_mut()
+            {
+
+                contacts.record(entity, normal);
+
+            }
+
+        }
+
+    }
+
+    if top
+    {
+
+        dynamic_body.top_collision = 0;
+
+    }
+
+}
+
+#[system(for_each)]
+fn oneway_collision(dynamic_body: &mut DynamicBody, velocity: &mut Velocity, _interacts: &InteractsWithOneWay, layers: Option<&Layers>, #[resource] broadphase: &Broadphase)
+{
+
+    let mut top = false;
+
+    //One way platforms only ever stop bodies landing on top of them, or on their underside while gravity is flipped
+    let floor = -dynamic_body.down();
+    let displacement = (velocity.x + dynamic_body.temp_velocity.x, velocity.y + dynamic_body.temp_velocity.y);
+
+    let nearby = broadphase.oneways(&collision_area(&dynamic_body.body, displacement), &Layers::or_default(layers));
+
+    if let Some((rect, _)) = earliest_hit(&dynamic_body.body, displacement, &nearby, (true, false)).filter(|(_, normal)| { return normal.1 == floor; })
+    {
+
+        snap_to_face(&mut dynamic_body.body, &rect, (0.0, floor));
+        velocity.y = 0.0;
+
+        top = true;
+
+    }
+
+	let mut collision_velocity = (velocity.x + dynamic_body.temp_velocity.x, velocity.y + dynamic_body.temp_velocity.y);
+
+    for (_, rect) in nearby.iter()
+    {
+
+        let correction = Rect::collides(&dynamic_body.body, rect, collision_velocity);
+
+        let landing = if dynamic_body.flipped() { correction.1 > 0.0 && dynamic_body.body.y - collision_velocity.1 >= rect.bottom() } else { correction.1 < 0.0 && dynamic_body.body.bottom() - collision_velocity.1 <= rect.y };
+
+        if landing
+        {
+
+            dynamic_body.body.y += correction.1;
+            velocity.y = 0.0;
+			collision_velocity.1 = 0.0;
+
+            top = true;
+
+        }
+
+    }
+
+    if top
+    {
+
+        dynamic_body.top_collision = 0;
+
+    }
+
+}
+
+//Portion of a horizontal overlap each body takes when separating
+fn push_shares(a: &Solid, b: &Solid) -> (f32, f32)
+{
+
+    match (a.pushable, b.pushable)
+    {
+
+        (true, true) => return (b.mass / (a.mass + b.mass), a.mass / (a.mass + b.mass)),
+        (true, false) => return (1.0, 0.0),
+        (false, true) => return (0.0, 1.0),
+        (false, false) => return (0.5, 0.5)
+
+    }
+
+}
+
+type SolidBody<'a> = (&'a mut DynamicBody, &'a mut Velocity, &'a Solid, Layers, Entity);
+
+//Whether the body cannot be pushed sideways without going into a wall, a polygon or a solid which cannot be pushed. The
+//bodies being separated never block each other
+fn pinned(body: &SolidBody, shift: f32, blockers: &Blockers, pair: &[Entity]) -> bool
+{
+
+    return blockers.blocks(body.0, (shift, 0.0), &body.3, pair);
+
+}
+
+fn separate(a: SolidBody, b: SolidBody, blockers: &Blockers)
+{
+
+    let pair = [a.4, b.4];
+
+    let (ra, rb) = (a.0.body, b.0.body);
+
+    if !Rect::intersects(&ra, &rb) || !Layers::interact(&a.3, &b.3)
+    {
+
+        return;
+
+    }
+
+    let (upper, lower) = if ra.y + ra.height * 0.5 < rb.y + rb.height * 0.5 { (a, b) } else { (b, a) };
+
+    //Bodies which were above the other before this step's move land on it, anything else is pushed sideways. Flipped bodies
+    //land on the underside of whatever is above them instead
+    if upper.0.body.bottom() - upper.1.y <= lower.0.body.y - lower.1.y + FLOATING_POINT_ERROR
+    {
+
+        if lower.0.flipped()
+        {
+
+            lower.0.body.y = upper.0.body.bottom();
+            lower.1.y = lower.1.y.max(upper.1.y);
+            lower.0.top_collision = 0;
+
+        }
+        else
+        {
+
+            upper.0.body.y = lower.0.body.y - upper.0.body.height;
+            upper.1.y = upper.1.y.min(lower.1.y);
+            upper.0.top_collision = 0;
+
+        }
+
+        return;
+
+    }
+
+    let (ra, rb) = (upper.0.body, lower.0.body);
+    let overlap = ra.right().min(rb.right()) - ra.x.max(rb.x);
+    let direction = if ra.x + ra.width * 0.5 < rb.x + rb.width * 0.5 { -1.0 } else { 1.0 };
+
+    let (mut upper_share, mut lower_share) = push_shares(upper.2, lower.2);
+
+    //Bodies pushed into a wall stay put and the pusher is stopped instead
+    if upper_share > 0.0 && pinned(&upper, direction * overlap * upper_share, blockers, &pair)
+    {
+
+        (upper_share, lower_share) = (0.0, 1.0);
+
+    }
+    else if lower_share > 0.0 && pinned(&lower, -direction * overlap * lower_share, blockers, &pair)
+    {
+
+        (upper_share, lower_share) = (1.0, 0.0);
+
+    }
+
+    upper.0.body.x += direction * overlap * upper_share;
+    lower.0.body.x -= direction * overlap * lower_share;
+
+}
+
+//Pairs are checked lowest first, so that stacks settle from the ground up
+#[system]
+#[write_component(DynamicBody)]
+#[write_component(Velocity)]
+#[read_component(Solid)]
+#[read_component(StaticBody)]
+#[read_component(Kinematic)]
+#[read_component(PolygonBody)]
+#[read_component(Layers)]
+fn dynamic_collision(world: &mut SubWorld, #[resource] broadphase: &Broadphase)
+{
+
+    let moving = Blockers::moving(world);
+
+    let mut query = <(Entity, &mut DynamicBody, &mut Velocity, &Solid, Option<&Layers>)>::query();
+    let (mut body_world, blocker_world) = world.split_for_query(&query);
+
+    let mut bodies: Vec<SolidBody> = query.iter_mut(&mut body_world).map(|(entity, body, velocity, solid, layers)| { return (body, velocity, solid, Layers::or_default(layers), *entity); }).collect();
+
+    bodies.sort_by(|a, b| { return b.0.body.bottom().total_cmp(&a.0.body.bottom()); });
+
+    for j in 1..bodies.len()
+    {
+
+        for i in 0..j
+        {
+
+            let (before, after) = bodies.split_at_mut(j);
+
+            let (a_body, a_velocity, a_solid, a_layers, a_entity) = &mut before[i];
+            let (b_body, b_velocity, b_solid, b_layers, b_entity) = &mut after[0];
+
+            if !Rect::intersects(&a_body.body, &b_body.body)
+            {
+
+                continue;
+
+            }
+
+            //Neither body is pushed further than they overlap, so only what is that close can pin them
+            let area = Rect::union(&a_body.body, &b_body.body).expand(a_body.body.width.max(b_body.body.width));
+            let blockers = Blockers::within(&area, &moving, broadphase, &blocker_world);
+
+            separate((a_body, a_velocity, a_solid, *a_layers, *a_entity), (b_body, b_velocity, b_solid, *b_layers, *b_entity), &blockers);
+
+        }
+
+    }
+
+}
+
+//Works out what each body is touching once everything has been resolved, and reports the contacts which started or ended
+#[system]
+#[read_component(DynamicBody)]
+#[read_component(Solid)]
+#[read_component(Layers)]
+#[read_component(InteractsWithOneWay)]
+#[write_component(Contacts)]
+fn update_contacts(world: &mut SubWorld, #[resource] broadphase: &Broadphase, #[resource] events: &mut ContactEvents)
+{
+
+    events.events.clear();
+
+    let mut solid_query = <(Entity, &DynamicBody, &Solid, Option<&Layers>)>::query();
+    let solids: Vec<(Entity, Rect, Layers)> = solid_query.iter(world).map(|(entity, dynamic, _, layers)| { return (*entity, dynamic.body, Layers::or_default(layers)); }).collect();
+
+    let mut query = <(Entity, &DynamicBody, &mut Contacts, Option<&Layers>, Option<&InteractsWithOneWay>)>::query();
+
+    for (entity, dynamic, contacts, layers, oneway) in query.iter_mut(world)
+    {
+
+        let layers = Layers::or_default(layers);
+        let area = dynamic.body.expand(CONTACT_DISTANCE);
+
+        let (floor, roof) = if dynamic.flipped() { (Side::Top, Side::Bottom) } else { (Side::Bottom, Side::Top) };
+
+        let mut touching = std::mem::take(&mut contacts.recorded);
+
+        let mut found: Vec<(Entity, Side)> = broadphase.statics(&area, &layers).iter()
+            .filter_map(|(entry, rect)| { return Side::touching(&dynamic.body, rect).map(|side| { return (entry.entity, side); }); })
+            .collect();
+
+        if oneway.is_some()
+        {
+
+            found.extend(broadphase.oneways(&area, &layers).iter()
+                .filter_map(|(entry, rect)| { return Side::touching(&dynamic.body, rect).map(|side| { return (entry.entity, side); }); })
+                .filter(|(_, side)| { return *side == floor; }));
+
+        }
+
+        found.extend(solids.iter()
+            .filter(|(other, _, other_layers)| { return other != entity && Layers::interact(&layers, other_layers); })
+            .filter_map(|(other, rect, _)| { return Side::touching(&dynamic.body, rect).map(|side| { return (*other, side); }); }));
+
+        touching.extend(found.into_iter().map(|(other, side)| { return Contact { other, side, normal: side.normal() }; }));
+
+        let mut unique: Vec<Contact> = Vec::new();
+
+        for contact in touching
+        {
+
+            if !unique.iter().any(|c| { return c.other == contact.other && c.side == contact.side; })
+            {
+
+                unique.push(contact);
+
+            }
+
+        }
+
+        let matches = |a: &Contact, b: &Contact| { return a.other == b.other && a.side == b.side; };
+
+        events.events.extend(unique.iter()
+            .filter(|contact| { return !contacts.touching.iter().any(|previous| { return matches(contact, previous); }); })
+            .map(|contact| { return ContactEvent { entity: *entity, contact: *contact, phase: ContactPhase::Begin }; }));
+
+        events.events.extend(contacts.touching.iter()
+            .filter(|previous| { return !unique.iter().any(|contact| { return matches(contact, previous); }); })
+            .map(|previous| { return ContactEvent { entity: *entity, contact: *previous, phase: ContactPhase::End }; }));
+
+        let on = |side: Side| { return unique.iter().any(|contact| { return contact.side == side; }); };
+
+        contacts.grounded = on(floor);
+        contacts.ceiling = on(roof);
+        contacts.wall_left = on(Side::Left);
+        contacts.wall_right = on(Side::Right);
+
+        contacts.touching = unique;
+        contacts.floor = floor;
+
+    }
+
+}
+
+//Everything a resized body could end up stuck inside
+struct Obstacles<'a>
+{
+
+    solids: Vec<(Entity, Rect, Layers)>,
+    polygons: Vec<(&'a PolygonBody, Layers)>,
+    broadphase: &'a Broadphase
+
+}
+
+impl Obstacles<'_>
+{
+
+    //One way platforms only block bodies which were already above them, since anything below passes up through them anyway
+    fn block(&self, rect: &Rect, old: &Rect, entity: Entity, layers: &Layers, oneway: bool) -> bool
+    {
+
+        if self.broadphase.statics(rect, layers).iter().any(|(_, r)| { return Rect::intersects(rect, r); })
+        {
+
+            return true;
+
+        }
+
+        if oneway && self.broadphase.oneways(rect, layers).iter().any(|(_, r)| { return Rect::intersects(rect, r) && old.bottom() <= r.y + FLOATING_POINT_ERROR; })
+        {
+
+            return true;
+
+        }
+
+        if self.solids.iter().any(|(other, r, l)| { return *other != entity && Layers::interact(layers, l) && Rect::intersects(rect, r); })
+        {
+
+            return true;
+
+        }
+
+        return self.polygons.iter()
+            .filter(|(polygon, l)| { return Layers::interact(layers, l) && (!polygon.oneway || (oneway && polygon.overlap(old).is_none())); })
+            .filter_map(|(polygon, _)| { return polygon.overlap(rect); })
+            .any(|mtv| { return mtv.dot(mtv).sqrt() > CONTACT_DISTANCE; });
+
+    }
+
+}
+
+//Solid bodies are only checked against other solid bodies, so that resizing a body never leaves it inside anything it collides with
+#[system]
+#[write_component(DynamicBody)]
+#[read_component(RequestSizeChange)]
+#[read_component(Solid)]
+#[read_component(Layers)]
+#[read_component(InteractsWithOneWay)]
+#[read_component(PolygonBody)]
+fn request_size_change(world: &mut SubWorld, cmd: &mut CommandBuffer, #[resource] broadphase: &Broadphase)
+{
+
+    let mut request_query = <&RequestSizeChange>::query();
+
+    if request_query.iter(world).next().is_none()
+    {
+
+        return;
+
+    }
+
+    let mut solid_query = <(Entity, &DynamicBody, &Solid, Option<&Layers>)>::query();
+    let solids: Vec<(Entity, Rect, Layers)> = solid_query.iter(world).map(|(entity, dynamic, _, layers)| { return (*entity, dynamic.body, Layers::or_default(layers)); }).collect();
+
+    let mut query = <(Entity, &mut DynamicBody, &RequestSizeChange, Option<&Layers>, Option<&InteractsWithOneWay>)>::query();
+    let (mut body_world, obstacle_world) = world.split_for_query(&query);
+
+    let mut polygon_query = <(&PolygonBody, Option<&Layers>)>::query();
+    let polygons: Vec<(&PolygonBody, Layers)> = polygon_query.iter(&obstacle_world).map(|(polygon, layers)| { return (polygon, Layers::or_default(layers)); }).collect();
+
+    let obstacles = Obstacles { solids, polygons, broadphase };
+
+    for (entity, dynamic_body, request, layers, oneway) in query.iter_mut(&mut body_world)
+    {
+
+        let layers = Layers::or_default(layers);
+        let old = dynamic_body.body;
+
+        let resized = request.anchor.resize(&old, request.width, request.height);
+
+        let fits = request.offsets().into_iter()
+            .map(|(x, y)| { return Rect { x: resized.x + x, y: resized.y + y, width: resized.width, height: resized.height }; })
+            .find(|rect| { return !obstacles.block(rect, &old, *entity, &layers, oneway.is_some()); });
+
+        match fits
+        {
+
+            Some(rect) =>
+            {
+
+                dynamic_body.body = rect;
+
+                cmd.add_component(*entity, RequestSizeChangeSuccess {});
+
+            },
+            None => cmd.add_component(*entity, RequestSizeChangeFailure {})
+
+        }
+
+        cmd.remove_component::<RequestSizeChange>(*entity);
+
+    }
+
+}
+
+#[system(for_each)]
+fn resize_failure(_fail: &RequestSizeChangeFailure, cmd: &mut CommandBuffer, entity: &Entity)
+{
+
+	cmd.remove_component::<RequestSizeChangeFailure>(*entity);
+
+}
+
+#[system(for_each)]
+fn crushed_cleanup(_crushed: &Crushed, cmd: &mut CommandBuffer, entity: &Entity)
+{
+
+    cmd.remove_component::<Crushed>(*entity);
+
+}
+
+#[system(for_each)]
+fn resize_success(_success: &RequestSizeChangeSuccess, cmd: &mut CommandBuffer, entity: &Entity)
+{
+
+	cmd.remove_component::<RequestSizeChangeSuccess>(*entity);
+
+}
+
+pub fn schedule_early_systems(schedule: &mut Builder)
+{
+
+    schedule.add_system(kinematic_toggle_system());
+    schedule.add_system(kinematic_stop_system());
+    schedule.add_system(kinematic_consume_stop_system());
+
+	schedule.add_system(reset_temp_velocity_system());
+	schedule.add_system(reset_oneway_system());
+	schedule.add_system(top_collision_system());
+
+}
+
+pub fn schedule_physics_systems(schedule: &mut Builder)
+{
+
+    schedule_force_systems(schedule);
+    schedule_movement_systems(schedule);
+
+}
+
+//Moves kinematic bodies and works out the velocity everything else will move with. Anything which sets velocities outright,
+//such as ropes, goes between these and the movement systems
+pub fn schedule_force_systems(schedule: &mut Builder)
+{
+
+    schedule.add_system(update_broadphase_system());
+
+	schedule.add_system(kinematic_static_move_system());
+    schedule.add_system(kinematic_oneway_move_system());
+    schedule.add_system(update_moving_broadphase_system());
+
+    schedule.add_system(gravity_system());
+    schedule.add_system(fluid_system());
+
+}
+
+//Moves dynamic bodies and resolves their collisions
+pub fn schedule_movement_systems(schedule: &mut Builder)
+{
+
+    schedule.add_system(velocity_system());
+    schedule.add_system(facing_system());
+
+    schedule.add_system(static_collision_system());
+    schedule.add_system(polygon_collision_system());
+    schedule.add_system(oneway_collision_system());
+    schedule.add_system(dynamic_collision_system());
+
+	schedule.add_system(request_size_change_system());
+
+    schedule.add_system(update_contacts_system());
+
+}
+
+pub fn schedule_cleanup_systems(schedule: &mut Builder)
+{
+
+	schedule_request_systems(schedule);
+
+    schedule.add_system(crushed_cleanup_system());
+
+}
+
+fn schedule_request_systems(schedule: &mut Builder)
+{
+
+	schedule.add_system(resize_failure_system());
+	schedule.add_system(resize_success_system());
+
+}
+
+#[cfg(test)]
+mod tests
+{
+
+    use super::*;
+
+    //Resources and a schedule which run whole steps of physics, as the game does
+    fn simulation() -> (Resources, Schedule)
+    {
+
+        let mut resources = Resources::default();
+
+        resources.insert(Timestep { step: 16 });
+        resources.insert(Gravity { force: 0.32, max: 16.0 });
+        resources.insert(Broadphase::new());
+        resources.insert(ContactEvents::new());
+
+        let mut builder = Schedule::builder();
+
+        schedule_early_systems(&mut builder);
+        schedule_physics_systems(&mut builder);
+        schedule_cleanup_systems(&mut builder);
+
+        return (resources, builder.build());
+
+    }
+
+    #[test]
+    fn contact_sides()
+    {
+
+        let body = Rect { x: 10.0, y: 10.0, width: 10.0, height: 20.0 };
+
+        assert_eq!(Side::touching(&body, &Rect { x: 0.0, y: 30.0, width: 100.0, height: 10.0 }), Some(Side::Bottom));
+        assert_eq!(Side::touching(&body, &Rect { x: 0.0, y: 0.0, width: 100.0, height: 10.0 }), Some(Side::Top));
+        assert_eq!(Side::touching(&body, &Rect { x: 0.0, y: 0.0, width: 10.0, height: 100.0 }), Some(Side::Left));
+        assert_eq!(Side::touching(&body, &Rect { x: 20.0, y: 0.0, width: 10.0, height: 100.0 }), Some(Side::Right));
+
+        //Corners only touching diagonally and surfaces with a gap are not contacts
+        assert_eq!(Side::touching(&body, &Rect { x: 20.0, y: 30.0, width: 10.0, height: 10.0 }), None);
+        assert_eq!(Side::touching(&body, &Rect { x: 0.0, y: 31.0, width: 100.0, height: 10.0 }), None);
+
+        assert_eq!(Side::from_normal(Point { x: -0.3, y: -0.95 }), Side::Bottom);
+        assert_eq!(Side::from_normal(Point { x: 0.0, y: 1.0 }), Side::Top);
+        assert_eq!(Side::from_normal(Point { x: 0.9, y: -0.4 }), Side::Left);
+        assert_eq!(Side::from_normal(Point { x: -1.0, y: 0.0 }), Side::Right);
+
+    }
+
+    #[test]
+    fn kinematic_waypoints()
+    {
+
+        let waypoints = vec!
+        [
+
+            Waypoint { point: Point { x: 0.0, y: 0.0 }, speed: 100.0, easing: None },
+            Waypoint { point: Point { x: 100.0, y: 0.0 }, speed: 50.0, easing: Some(Easing::InOut) },
+            Waypoint { point: Point { x: 100.0, y: 100.0 }, speed: 100.0, easing: None }
+
+        ];
+
+        //One second along the first leg, then two seconds eased down the second
+        let mut kinematic = Kinematic::from_waypoints(&waypoints, false).with_motion(Motion::PingPong, 500, true);
+
+        assert_eq!(kinematic.max, 3000);
+        assert_eq!(kinematic.update(500), (50.0, 0.0));
+        assert_eq!(kinematic.update(1500), (100.0, 50.0));
+
+        //Turns round at the end and waits there before heading back
+        assert_eq!(kinematic.update(1000), (100.0, 100.0));
+        assert_eq!(kinematic.update(500), (100.0, 100.0));
+        assert_eq!(kinematic.update(1000), (100.0, 50.0));
+
+        //Looping paths head back to the start, and one shot paths stay at the end
+        let mut looped = Kinematic::from_waypoints(&waypoints, true).with_motion(Motion::Loop, 0, true);
+
+        assert_eq!(looped.max, 3000 + 1414);
+        assert_eq!(looped.update(4414), (0.0, 0.0));
+        assert_eq!(looped.update(500), (50.0, 0.0));
+
+        let mut once = Kinematic::from_waypoints(&waypoints, false).with_motion(Motion::OneShot, 0, false);
+
+        assert_eq!(once.update(1000), (0.0, 0.0));
+
+        once.start(1);
+
+        assert_eq!(once.update(5000), (100.0, 100.0));
+        assert_eq!(once.update(1000), (100.0, 100.0));
+        assert_eq!(once.direction, 0);
+
+    }
+
+    #[test]
+    fn resize_anchors()
+    {
+
+        let rect = Rect { x: 10.0, y: 10.0, width: 20.0, height: 40.0 };
+
+        assert_eq!(Anchor::Bottom.resize(&rect, 10.0, 20.0), Rect { x: 10.0, y: 30.0, width: 10.0, height: 20.0 });
+        assert_eq!(Anchor::BottomCentre.resize(&rect, 10.0, 20.0), Rect { x: 15.0, y: 30.0, width: 10.0, height: 20.0 });
+        assert_eq!(Anchor::Top.resize(&rect, 10.0, 20.0), Rect { x: 10.0, y: 10.0, width: 10.0, height: 20.0 });
+        assert_eq!(Anchor::Centre.resize(&rect, 10.0, 20.0), Rect { x: 15.0, y: 20.0, width: 10.0, height: 20.0 });
+        assert_eq!(Anchor::Left.resize(&rect, 30.0, 20.0), Rect { x: 10.0, y: 30.0, width: 30.0, height: 20.0 });
+        assert_eq!(Anchor::Right.resize(&rect, 30.0, 20.0), Rect { x: 0.0, y: 30.0, width: 30.0, height: 20.0 });
+
+        //Nudges try the body where it is first, then work outwards
+        assert_eq!(RequestSizeChange::new(1.0, 1.0).offsets(), vec![(0.0, 0.0)]);
+        assert_eq!(RequestSizeChange::new(1.0, 1.0).with_nudge(1.5).offsets(), vec![(0.0, 0.0), (-1.0, 0.0), (1.0, 0.0), (0.0, -1.0), (0.0, 1.0), (-1.5, 0.0), (1.5, 0.0), (0.0, -1.5), (0.0, 1.5)]);
+
+    }
+
+    #[test]
+    fn resize_under_polygon()
+    {
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+
+        resources.insert(Broadphase::new());
+
+        let mut schedule = Schedule::builder().add_system(request_size_change_system()).build();
+
+        world.push((PolygonBody::new(vec![Point { x: 0.0, y: 0.0 }, Point { x: 100.0, y: 0.0 }, Point { x: 100.0, y: 20.0 }, Point { x: 0.0, y: 20.0 }]),));
+
+        let fits = world.push((DynamicBody::new(10.0, 50.0, 15.0, 30.0), RequestSizeChange::new(15.0, 50.0)));
+        let blocked = world.push((DynamicBody::new(50.0, 50.0, 15.0, 30.0), RequestSizeChange::new(15.0, 70.0)));
+
+        schedule.execute(&mut world, &mut resources);
+
+        let entry = world.entry(fits).unwrap();
+
+        assert_eq!(entry.get_component::<DynamicBody>().unwrap().body, Rect { x: 10.0, y: 30.0, width: 15.0, height: 50.0 });
+        assert!(entry.get_component::<RequestSizeChangeSuccess>().is_ok());
+
+        let entry = world.entry(blocked).unwrap();
+
+        assert_eq!(entry.get_component::<DynamicBody>().unwrap().body, Rect { x: 50.0, y: 50.0, width: 15.0, height: 30.0 });
+        assert!(entry.get_component::<RequestSizeChangeFailure>().is_ok());
+        assert!(entry.get_component::<RequestSizeChange>().is_err());
+
+    }
+
+    #[test]
+    fn broadphase_rebuilds()
+    {
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+
+        resources.insert(Broadphase::new());
+
+        let mut schedule = Schedule::builder().add_system(update_broadphase_system()).build();
+
+        let area = Rect { x: 0.0, y: 0.0, width: 1000.0, height: 1000.0 };
+        let found = |resources: &Resources| -> Vec<Entity> { return resources.get::<Broadphase>().unwrap().statics(&area, &Layers::default()).iter().map(|(entry, _)| { return entry.entity; }).collect(); };
+
+        let first = world.push((StaticBody { body: Rect { x: 0.0, y: 0.0, width: 10.0, height: 10.0 } },));
+
+        schedule.execute(&mut world, &mut resources);
+
+        assert_eq!(found(&resources), vec![first]);
+
+        //Bodies which never move are left where they were sorted until the broadphase is marked dirty
+        world.remove(first);
+        let second = world.push((StaticBody { body: Rect { x: 100.0, y: 0.0, width: 10.0, height: 10.0 } },));
+
+        schedule.execute(&mut world, &mut resources);
+
+        assert_eq!(found(&resources), vec![first]);
+
+        resources.get_mut::<Broadphase>().unwrap().mark_dirty();
+        schedule.execute(&mut world, &mut resources);
+
+        assert_eq!(found(&resources), vec![second]);
+
+        //Moving bodies are gathered every step
+        let waypoints = [Waypoint { point: Point { x: 200.0, y: 0.0 }, speed: 100.0, easing: None }, Waypoint { point: Point { x: 200.0, y: 100.0 }, speed: 100.0, easing: None }];
+        let lift = world.push((StaticBody { body: Rect { x: 200.0, y: 0.0, width: 10.0, height: 10.0 } }, Kinematic::from_waypoints(&waypoints, false)));
+
+        schedule.execute(&mut world, &mut resources);
+
+        assert_eq!(found(&resources), vec![second, lift]);
+
+    }
+
+    #[test]
+    fn body_gravity()
+    {
+
+        let gravity = Gravity { force: 1.0, max: 10.0 };
+        let scale = GravityScale { scale: 0.5, max: 0.2 };
+        let zone = GravityZone { rect: Rect { x: 0.0, y: 0.0, width: 100.0, height: 100.0 }, strength: 2.0, flip: true };
+
+        let normal = BodyGravity::new(&gravity, None, None);
+
+        assert_eq!(normal.apply(0.0), 1.0);
+        assert_eq!(normal.apply(9.5), 10.0);
+
+        let scaled = BodyGravity::new(&gravity, Some(&scale), None);
+
+        assert_eq!(scaled.apply(0.0), 0.5);
+        assert_eq!(scaled.apply(1.9), 2.0);
+
+        //Zones scale the force on top of the body's own scale and flip which way is down
+        let flipped = BodyGravity::new(&gravity, Some(&scale), Some(&zone));
+
+        assert_eq!(flipped, BodyGravity { force: 1.0, max: 2.0, down: -1.0 });
+        assert_eq!(flipped.apply(0.0), -1.0);
+        assert_eq!(flipped.apply(-1.5), -2.0);
+        assert_eq!(flipped.apply(3.0), 2.0);
+
+        assert!(zone.contains(&Rect { x: 90.0, y: 90.0, width: 18.0, height: 18.0 }));
+        assert!(!zone.contains(&Rect { x: 90.0, y: 90.0, width: 22.0, height: 22.0 }));
+
+    }
+
+    #[test]
+    fn flipped_landing()
+    {
+
+        let mut world = World::default();
+        let (mut resources, mut schedule) = simulation();
+
+        world.push((GravityZone { rect: Rect { x: 0.0, y: 0.0, width: 1000.0, height: 1000.0 }, strength: 1.0, flip: true },));
+        world.push((StaticBody { body: Rect { x: 0.0, y: 0.0, width: 200.0, height: 20.0 } },));
+        world.push((OneWayBody { body: Rect { x: 300.0, y: 100.0, width: 100.0, height: 2.0 } },));
+
+        let under_static = world.push((DynamicBody::new(50.0, 200.0, 15.0, 30.0), Velocity::new(0.0, 0.0), HasGravity {}, Contacts::new()));
+        let under_oneway = world.push((DynamicBody::new(340.0, 200.0, 15.0, 30.0), Velocity::new(0.0, 0.0), HasGravity {}, InteractsWithOneWay {}, Contacts::new()));
+
+        for _ in 0..120
+        {
+
+            schedule.execute(&mut world, &mut resources);
+
+        }
+
+        //Both fall upwards and land on the underside of what is above them, which counts as their ground
+        for (entity, ceiling) in [(under_static, 20.0), (under_oneway, 102.0)]
+        {
+
+            let entry = world.entry(entity).unwrap();
+            let dynamic = entry.get_component::<DynamicBody>().unwrap();
+
+            assert!(dynamic.flipped());
+            assert_eq!(dynamic.top_collision, 0);
+            assert!((dynamic.body.y - ceiling).abs() < 0.01);
+            assert!(entry.get_component::<Contacts>().unwrap().grounded);
+
+        }
+
+    }
+
+    #[test]
+    fn oneway_polygon()
+    {
+
+        let mut world = World::default();
+        let (mut resources, mut schedule) = simulation();
+
+        let ramp = vec![Point { x: 100.0, y: 300.0 }, Point { x: 200.0, y: 250.0 }, Point { x: 300.0, y: 250.0 }, Point { x: 300.0, y: 300.0 }];
+
+        world.push((StaticBody { body: Rect { x: 0.0, y: 300.0, width: 1000.0, height: 20.0 } },));
+        world.push((PolygonBody::new(ramp).with_oneway(true),));
+
+        let landing = world.push((DynamicBody::new(240.0, 100.0, 15.0, 30.0), Velocity::new(0.0, 0.0), HasGravity {}, InteractsWithOneWay {}));
+        let dropping = world.push((DynamicBody::new(260.0, 100.0, 15.0, 30.0), Velocity::new(0.0, 0.0), HasGravity {}));
+        let walking = world.push((DynamicBody::new(20.0, 270.0, 15.0, 30.0), Velocity::new(2.0, 0.0), HasGravity {}, InteractsWithOneWay {}));
+        let jumping = world.push((DynamicBody::new(220.0, 270.0, 15.0, 30.0), Velocity::new(0.0, -8.0), HasGravity {}, InteractsWithOneWay {}));
+
+        for _ in 0..120
+        {
+
+            schedule.execute(&mut world, &mut resources);
+
+        }
+
+        //Bodies which do not interact with one way bodies fall through to the floor, the others stand on top, including
+        //those which walked up the slope from the side or jumped up through it from below
+        for (entity, y) in [(landing, 220.0), (dropping, 270.0), (walking, 220.0), (jumping, 220.0)]
+        {
+
+            let entry = world.entry(entity).unwrap();
+
+            assert!((entry.get_component::<DynamicBody>().unwrap().body.y - y).abs() < 0.01);
+
+        }
+
+    }
+
+    #[test]
+    fn flipped_fluid()
+    {
+
+        let mut world = World::default();
+        let (mut resources, mut schedule) = simulation();
+
+        world.push((GravityZone { rect: Rect { x: 0.0, y: 0.0, width: 1000.0, height: 1000.0 }, strength: 1.0, flip: true },));
+        world.push((Fluid { rect: Rect { x: 0.0, y: 0.0, width: 200.0, height: 1000.0 }, buoyancy: 1.5, drag: 2.0, max_fall: 60.0, clarity: 1.0 },));
+        world.push((Fluid { rect: Rect { x: 300.0, y: 0.0, width: 200.0, height: 1000.0 }, buoyancy: 0.5, drag: 2.0, max_fall: 60.0, clarity: 1.0 },));
+
+        let floating = world.push((DynamicBody::new(50.0, 500.0, 20.0, 20.0), Velocity::new(0.0, 0.0), HasGravity {}));
+        let sinking = world.push((DynamicBody::new(350.0, 500.0, 20.0, 20.0), Velocity::new(0.0, -20.0), HasGravity {}));
+
+        for _ in 0..30
+        {
+
+            schedule.execute(&mut world, &mut resources);
+
+        }
+
+        //Buoyancy pushes against the flipped gravity and sinking is capped on the way up
+        let entry = world.entry(floating).unwrap();
+
+        assert!(entry.get_component::<DynamicBody>().unwrap().body.y > 500.0);
+
+        let entry = world.entry(sinking).unwrap();
+
+        assert!(entry.get_component::<DynamicBody>().unwrap().body.y < 500.0);
+        assert!((entry.get_component::<Velocity>().unwrap().y + 60.0 * 0.016).abs() < 0.001);
+
+    }
+
+    #[test]
+    fn layer_names()
+    {
+
+        let names = |list: &[&str]| -> Option<Vec<String>> { return Some(list.iter().map(|name| { return name.to_string(); }).collect()); };
+
+        assert_eq!(Layers::from_names(&None, &None), Layers::default());
+        assert_eq!(Layers::from_names(&names(&["guard"]), &names(&["world", "player"])), Layers::new(LAYER_GUARD, LAYER_WORLD | LAYER_PLAYER));
+
+        //Only the lists given replace the defaults
+        let crates = Layers::new(LAYER_CRATE, LAYER_ALL);
+
+        assert_eq!(crates.with_names(&None, &None), crates);
+        assert_eq!(crates.with_names(&None, &names(&["world"])), Layers::new(LAYER_CRATE, LAYER_WORLD));
+
+        assert!(!Layers::interact(&crates.with_names(&None, &names(&["world"])), &Layers::new(LAYER_GUARD, LAYER_ALL)));
+
+    }
+
+    //Raises a lift with a body standing on it towards whatever the ceiling is. Returns the highest the lift got, where it
+    //ended up and whether the body was crushed
+    fn squeeze_under(crush: Crush, ceiling: fn(&mut World)) -> (f32, f32, bool)
+    {
+
+        let mut world = World::default();
+        let (mut resources, mut schedule) = simulation();
+
+        ceiling(&mut world);
+
+        let waypoints = [Waypoint { point: Point { x: 0.0, y: 200.0 }, speed: 100.0, easing: None }, Waypoint { point: Point { x: 0.0, y: 0.0 }, speed: 100.0, easing: None }];
+        let lift = world.push((Kinematic::from_waypoints(&waypoints, false).with_motion(Motion::PingPong, 0, true).with_crush(crush), StaticBody { body: Rect { x: 0.0, y: 200.0, width: 100.0, height: 20.0 } }));
+        let rider = world.push((DynamicBody::new(20.0, 150.0, 15.0, 50.0), Velocity::new(0.0, 0.0), HasGravity {}));
+
+        let (mut highest, mut crushed) = (200.0f32, false);
+
+        for _ in 0..150
+        {
+
+            schedule.execute(&mut world, &mut resources);
+
+            highest = highest.min(world.entry(lift).unwrap().get_component::<StaticBody>().unwrap().body.y);
+            crushed |= world.entry(rider).unwrap().get_component::<Crushed>().is_ok();
+
+        }
+
+        return (highest, world.entry(lift).unwrap().get_component::<StaticBody>().unwrap().body.y, crushed);
+
+    }
+
+    #[test]
+    fn kinematic_push()
+    {
+
+        let (highest, _, crushed) = squeeze_under(Crush::Push, |world| { world.push((StaticBody { body: Rect { x: 0.0, y: 0.0, width: 100.0, height: 20.0 } },)); });
+
+        //Platforms keep going by default, just as they always have
+        assert!(highest < 70.0);
+        assert!(!crushed);
+
+    }
+
+    #[test]
+    fn kinematic_stop()
+    {
+
+        let (highest, end, crushed) = squeeze_under(Crush::Stop, |world| { world.push((StaticBody { body: Rect { x: 0.0, y: 0.0, width: 100.0, height: 20.0 } },)); });
+
+        //Waits with the rider pressed against the ceiling, well short of the end of its path
+        assert!((highest - 70.0).abs() < 1.0);
+        assert!((end - highest).abs() < 1.0);
+        assert!(!crushed);
+
+    }
+
+    #[test]
+    fn kinematic_reverse()
+    {
+
+        //Guards and other solids which cannot be pushed stop the lift as well as walls do
+        let (highest, end, crushed) = squeeze_under(Crush::Reverse, |world| { world.push((DynamicBody::new(0.0, 0.0, 100.0, 20.0), Velocity::new(0.0, 0.0), Solid { mass: 2.0, pushable: false })); });
+
+        assert!((highest - 70.0).abs() < 1.0);
+        assert!(end > highest + 50.0);
+        assert!(!crushed);
+
+    }
+
+    #[test]
+    fn kinematic_crush()
+    {
+
+        let (highest, _, crushed) = squeeze_under(Crush::Crush, |world| { world.push((PolygonBody::new(vec![Point { x: 0.0, y: 0.0 }, Point { x: 100.0, y: 0.0 }, Point { x: 100.0, y: 20.0 }, Point { x: 0.0, y: 20.0 }]),)); });
+
+        assert!(highest < 70.0);
+        assert!(crushed);
+
+    }
+
+    #[test]
+    fn push_shares_by_mass()
+    {
+
+        let light = Solid { mass: 1.0, pushable: true };
+        let heavy = Solid { mass: 3.0, pushable: true };
+        let fixed = Solid { mass: 1.0, pushable: false };
+
+        assert_eq!(push_shares(&light, &heavy), (0.75, 0.25));
+        assert_eq!(push_shares(&heavy, &light), (0.25, 0.75));
+        assert_eq!(push_shares(&light, &fixed), (1.0, 0.0));
+        assert_eq!(push_shares(&fixed, &light), (0.0, 1.0));
+        assert_eq!(push_shares(&fixed, &fixed), (0.5, 0.5));
+
+    }
+
+    #[test]
+    fn separate_solids()
+    {
+
+        let mut world = World::default();
+        let (light, heavy, wall) = (world.push((0,)), world.push((1,)), world.push((2,)));
+
+        let solids = (Solid { mass: 1.0, pushable: true }, Solid { mass: 3.0, pushable: true });
+
+        //Separates a light body falling at the given speed from a heavy one, returning where both end up and the light body's speed
+        let run = |blockers: &Blockers, a: Rect, b: Rect, fall: f32| -> (Rect, Rect, f32)
+        {
+
+            let (mut a_body, mut b_body) = (DynamicBody::new(a.x, a.y, a.width, a.height), DynamicBody::new(b.x, b.y, b.width, b.height));
+            let (mut a_velocity, mut b_velocity) = (Velocity::new(0.0, fall), Velocity::new(0.0, 0.0));
+
+            separate((&mut a_body, &mut a_velocity, &solids.0, Layers::default(), light), (&mut b_body, &mut b_velocity, &solids.1, Layers::default(), heavy), blockers);
+
+            return (a_body.body, b_body.body, a_velocity.y);
+
+        };
+
+        let open = Blockers { rects: Vec::new(), polygons: Vec::new() };
+        let (a, b) = (Rect { x: 0.0, y: 0.0, width: 20.0, height: 20.0 }, Rect { x: 15.0, y: 0.0, width: 20.0, height: 20.0 });
+
+        //Side by side, the lighter body gives way more
+        let (a_end, b_end, _) = run(&open, a, b, 0.0);
+
+        assert_eq!((a_end.x, b_end.x), (-3.75, 16.25));
+
+        //Walls, solids which cannot be pushed and polygons all pin the light body, so the heavy one takes the whole overlap
+        let slab = PolygonBody::new(vec![Point { x: -10.0, y: -5.0 }, Point { x: 0.0, y: -5.0 }, Point { x: 0.0, y: 25.0 }, Point { x: -10.0, y: 25.0 }]);
+        let pins = [
+            Blockers { rects: vec![(wall, Rect { x: -10.0, y: 0.0, width: 10.0, height: 20.0 }, Layers::default())], polygons: Vec::new() },
+            Blockers { rects: vec![(wall, Rect { x: -10.0, y: -5.0, width: 10.0, height: 30.0 }, Layers::default())], polygons: Vec::new() },
+            Blockers { rects: Vec::new(), polygons: vec![(&slab, Layers::default())] }
+        ];
+
+        for blockers in pins.iter()
+        {
+
+            let (a_end, b_end, _) = run(blockers, a, b, 0.0);
+
+            assert_eq!((a_end.x, b_end.x), (0.0, 20.0));
+
+        }
+
+        //Walls on layers the body ignores do not pin it
+        let ghost = Blockers { rects: vec![(wall, Rect { x: -10.0, y: 0.0, width: 10.0, height: 20.0 }, Layers::new(LAYER_WORLD, LAYER_GUARD))], polygons: Vec::new() };
+
+        assert_eq!(run(&ghost, a, b, 0.0).0.x, -3.75);
+
+        //A body which was above the other before it moved lands on it instead
+        let (a_end, b_end, fall) = run(&open, Rect { x: 5.0, y: 0.0, width: 20.0, height: 20.0 }, Rect { x: 0.0, y: 15.0, width: 20.0, height: 20.0 }, 5.0);
+
+        assert_eq!((a_end.x, a_end.y, b_end.y, fall), (5.0, -5.0, 15.0, 0.0));
+
+    }
+
+}
@@ -0,0 +1,583 @@
+use legion::*;
+use legion::world::SubWorld;
+use legion::systems::Builder;
+
+use serde::Deserialize;
+
+use super::physics::{DynamicBody, Gravity, OneWayBody, StaticBody, Velocity};
+use super::space::{Point, Segment};
+
+//How many times each step the segment lengths are enforced. More passes make the rope stiffer
+const ROPE_ITERATIONS: usize = 10;
+
+//Fraction of each point's motion kept from one step to the next
+const ROPE_DAMPING: f32 = 0.995;
+
+//How much lighter a held point is moved by the segments around it, compared to a free one. Stops the rope from dragging
+//whatever is hanging off it around
+const HELD_WEIGHT: f32 = 0.1;
+
+const DEFAULT_SEGMENTS: usize = 12;
+
+//Level data for a rope hanging from a fixed anchor. Ropes hanging from platforms give the anchor relative to the platform's
+//top left corner instead
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct RopeData
+{
+
+    pub anchor: Point,
+    pub length: f32,
+    pub segments: Option<usize>
+
+}
+
+//Verlet rope, made of points joined by segments which can go slack but are pulled back when stretched. The first point is
+//pinned to the anchor, which follows a platform if the rope hangs from one
+pub struct Rope
+{
+
+    pub anchor: Point,
+    pub points: Vec<Point>,
+    previous: Vec<Point>,
+    segment: f32,
+    platform: Option<(Entity, Point)>
+
+}
+
+impl Rope
+{
+
+    //Starts out hanging straight down from the anchor
+    pub fn new(anchor: Point, length: f32, segments: usize) -> Rope
+    {
+
+        let segments = segments.max(1);
+        let segment = length / segments as f32;
+
+        let points: Vec<Point> = (0..=segments).map(|i| { return Point { x: anchor.x, y: anchor.y + segment * i as f32 }; }).collect();
+
+        return Rope { anchor, previous: points.clone(), points, segment, platform: None };
+
+    }
+
+    pub fn from_data(data: &RopeData) -> Rope
+    {
+
+        return Rope::new(data.anchor, data.length, data.segments.unwrap_or(DEFAULT_SEGMENTS));
+
+    }
+
+    //Hangs the rope from a static or one way body, at an offset from its top left corner
+    pub fn with_platform(mut self, platform: Entity, offset: Point) -> Rope
+    {
+
+        self.platform = Some((platform, offset));
+
+        return self;
+
+    }
+
+    pub fn segment(&self) -> f32
+    {
+
+        return self.segment;
+
+    }
+
+    pub fn segments(&self) -> Vec<Segment>
+    {
+
+        return self.points.windows(2).map(|pair| { return Segment::new(pair[0], pair[1]); }).collect();
+
+    }
+
+    //The closest point which can be held within reach of the given position. The anchor itself cannot be held
+    pub fn nearest(&self, position: Point, reach: f32) -> Option<(usize, f32)>
+    {
+
+        return self.points.iter().enumerate().skip(1)
+            .map(|(i, point)| { return (i, distance(*point, position)); })
+            .filter(|(_, distance)| { return *distance <= reach; })
+            .min_by(|a, b| { return a.1.total_cmp(&b.1); });
+
+    }
+
+    //Moves every point by the motion it had last step plus gravity, then pulls the segments back to length. Held points
+    //are pushed along by whoever is holding them
+    fn simulate(&mut self, gravity: f32, grips: &[(usize, Point)])
+    {
+
+        for i in 1..self.points.len()
+        {
+
+            let point = self.points[i];
+            let motion = (point - self.previous[i]).scale(ROPE_DAMPING);
+
+            self.previous[i] = point;
+            self.points[i] = point + motion + Point { x: 0.0, y: gravity };
+
+        }
+
+        for (index, push) in grips.iter()
+        {
+
+            self.points[*index] = self.points[*index] + *push;
+
+        }
+
+        let weights: Vec<f32> = (0..self.points.len()).map(|i|
+        {
+
+            if i == 0
+            {
+
+                return 0.0;
+
+            }
+
+            return if grips.iter().any(|(index, _)| { return *index == i; }) { HELD_WEIGHT } else { 1.0 };
+
+        }).collect();
+
+        for _ in 0..ROPE_ITERATIONS
+        {
+
+            self.points[0] = self.anchor;
+
+            for i in 1..self.points.len()
+            {
+
+                let delta = self.points[i] - self.points[i - 1];
+                let length = distance(self.points[i], self.points[i - 1]);
+
+                //Slack segments are left alone, so the rope bunches up rather than acting like a rod
+                if length <= self.segment
+                {
+
+                    continue;
+
+                }
+
+                let total = weights[i - 1] + weights[i];
+                let correction = delta.scale((length - self.segment) / (length * total));
+
+                self.points[i - 1] = self.points[i - 1] + correction.scale(weights[i - 1]);
+                self.points[i] = self.points[i] - correction.scale(weights[i]);
+
+            }
+
+        }
+
+    }
+
+    //Gives a point the motion of something which has just grabbed it, so that jumping onto a rope starts it swinging
+    fn carry(&mut self, index: usize, momentum: Point)
+    {
+
+        self.previous[index] = self.points[index] - momentum;
+
+    }
+
+}
+
+fn distance(a: Point, b: Point) -> f32
+{
+
+    let delta = a - b;
+
+    return delta.dot(delta).sqrt();
+
+}
+
+//Where a body holds a rope, in the middle of its top edge
+pub fn hands(body: &DynamicBody) -> Point
+{
+
+    return Point { x: body.body.x + body.body.width * 0.5, y: body.body.y };
+
+}
+
+//A body hanging from one of a rope's points. While held, the body's velocity is worked out from the rope, so letting go
+//keeps whatever momentum the swing had
+pub struct RopeGrip
+{
+
+    pub rope: Entity,
+    pub index: usize,
+    pub push: Point, //Added to the held point each step, in pixels per step squared
+    momentum: Option<Point>,
+    climbed: f32
+
+}
+
+impl RopeGrip
+{
+
+    pub fn new(rope: Entity, index: usize, momentum: Point) -> RopeGrip
+    {
+
+        return RopeGrip { rope, index, push: Point { x: 0.0, y: 0.0 }, momentum: Some(momentum), climbed: 0.0 };
+
+    }
+
+    //Moves the grip along the rope, towards the anchor for negative distances, a whole segment at a time
+    pub fn climb(&mut self, rope: &Rope, distance: f32)
+    {
+
+        self.climbed += distance;
+
+        let last = rope.points.len() - 1;
+
+        while self.climbed.abs() >= rope.segment
+        {
+
+            let step = self.climbed.signum();
+
+            self.climbed -= step * rope.segment;
+
+            if step < 0.0
+            {
+
+                self.index = self.index.saturating_sub(1).max(1);
+
+            }
+            else
+            {
+
+                self.index = (self.index + 1).min(last);
+
+            }
+
+        }
+
+    }
+
+}
+
+//Runs after gravity and before velocity, so that bodies holding ropes are given the velocity which takes them to their
+//point on the rope, instead of falling
+#[system]
+#[read_component(StaticBody)]
+#[read_component(OneWayBody)]
+#[read_component(DynamicBody)]
+#[write_component(Velocity)]
+#[write_component(RopeGrip)]
+#[write_component(Rope)]
+pub fn simulate_ropes(world: &mut SubWorld, #[resource] gravity: &Gravity)
+{
+
+    //Ropes hanging from platforms follow them wherever they have moved this step
+    let mut rope_query = <(Entity, &Rope)>::query();
+    let anchors: Vec<(Entity, Point)> = rope_query.iter(world)
+        .filter_map(|(entity, rope)| { return rope.platform.map(|platform| { return (*entity, platform); }); })
+        .filter_map(|(entity, (platform, offset))|
+        {
+
+            let entry = world.entry_ref(platform).ok()?;
+
+            let body = entry.get_component::<StaticBody>().map(|body| { return body.body; })
+                .or_else(|_| { return entry.get_component::<OneWayBody>().map(|body| { return body.body; }); })
+                .ok()?;
+
+            return Some((entity, Point { x: body.x + offset.x, y: body.y + offset.y }));
+
+        })
+        .collect();
+
+    let mut grip_query = <(&mut RopeGrip, &DynamicBody)>::query();
+    let grips: Vec<(Entity, usize, Point, Option<Point>)> = grip_query.iter_mut(world)
+        .map(|(grip, _)| { return (grip.rope, grip.index, grip.push, grip.momentum.take()); })
+        .collect();
+
+    let mut ropes = <(Entity, &mut Rope)>::query();
+
+    for (entity, rope) in ropes.iter_mut(world)
+    {
+
+        if let Some((_, anchor)) = anchors.iter().find(|(rope, _)| { return rope == entity; })
+        {
+
+            rope.anchor = *anchor;
+
+        }
+
+        let held: Vec<(usize, Point)> = grips.iter()
+            .filter(|(rope, _, _, _)| { return rope == entity; })
+            .map(|(_, index, push, _)| { return (*index, *push); })
+            .collect();
+
+        for (_, index, _, momentum) in grips.iter().filter(|(rope, _, _, _)| { return rope == entity; })
+        {
+
+            if let Some(momentum) = momentum
+            {
+
+                rope.carry(*index, *momentum);
+
+            }
+
+        }
+
+        rope.simulate(gravity.force, &held);
+
+    }
+
+    let mut holder_query = <(&RopeGrip, &DynamicBody, &mut Velocity)>::query();
+    let (mut holder_world, rope_world) = world.split_for_query(&holder_query);
+
+    for (grip, dynamic, velocity) in holder_query.iter_mut(&mut holder_world)
+    {
+
+        let point = rope_world.entry_ref(grip.rope).ok()
+            .and_then(|entry| { return entry.get_component::<Rope>().ok().and_then(|rope| { return rope.points.get(grip.index).copied(); }); });
+
+        if let Some(point) = point
+        {
+
+            let target = point - hands(dynamic);
+
+            velocity.x = target.x;
+            velocity.y = target.y;
+
+        }
+
+    }
+
+}
+
+//Runs once collisions are resolved. A held body which hit something could not reach its point on the rope, so the rope is
+//pulled back to where the body actually ended up
+#[system]
+#[read_component(DynamicBody)]
+#[read_component(RopeGrip)]
+#[write_component(Rope)]
+pub fn settle_grips(world: &mut SubWorld)
+{
+
+    let mut grip_query = <(&RopeGrip, &DynamicBody)>::query();
+    let held: Vec<(Entity, usize, Point)> = grip_query.iter(world).map(|(grip, dynamic)| { return (grip.rope, grip.index, hands(dynamic)); }).collect();
+
+    let mut rope_query = <&mut Rope>::query();
+
+    for (entity, index, position) in held
+    {
+
+        if let Ok(rope) = rope_query.get_mut(world, entity)
+        {
+
+            if index < rope.points.len()
+            {
+
+                rope.points[index] = position;
+
+            }
+
+        }
+
+    }
+
+}
+
+//Hands bodies holding ropes their velocity, so goes between the physics force and movement systems
+pub fn schedule_rope_systems(schedule: &mut Builder)
+{
+
+    schedule.add_system(simulate_ropes_system());
+
+}
+
+//Goes after the physics movement systems, once held bodies have collided with whatever was in the way
+pub fn schedule_grip_systems(schedule: &mut Builder)
+{
+
+    schedule.add_system(settle_grips_system());
+
+}
+
+#[cfg(test)]
+mod tests
+{
+
+    use super::*;
+    use super::super::game::Timestep;
+    use super::super::physics::{self, Broadphase, ContactEvents, HasGravity, Kinematic, Motion, Waypoint};
+    use super::super::space::Rect;
+
+    //Resources and a schedule which run whole steps of physics with ropes, as the game does
+    fn simulation() -> (Resources, Schedule)
+    {
+
+        let mut resources = Resources::default();
+
+        resources.insert(Timestep { step: 16 });
+        resources.insert(Gravity { force: 0.32, max: 16.0 });
+        resources.insert(Broadphase::new());
+        resources.insert(ContactEvents::new());
+
+        let mut builder = Schedule::builder();
+
+        physics::schedule_early_systems(&mut builder);
+        physics::schedule_force_systems(&mut builder);
+        schedule_rope_systems(&mut builder);
+        physics::schedule_movement_systems(&mut builder);
+        schedule_grip_systems(&mut builder);
+        physics::schedule_cleanup_systems(&mut builder);
+
+        return (resources, builder.build());
+
+    }
+
+    //A body holding the bottom of a rope hanging from the origin, having grabbed it while moving right
+    fn swinger(world: &mut World) -> (Entity, Entity)
+    {
+
+        let rope = world.push((Rope::new(Point { x: 0.0, y: 0.0 }, 100.0, 10),));
+        let holder = world.push((DynamicBody::new(-7.5, 100.0, 15.0, 30.0), Velocity::new(0.0, 0.0), HasGravity {}, RopeGrip::new(rope, 10, Point { x: 4.0, y: 0.0 })));
+
+        return (rope, holder);
+
+    }
+
+    //The velocity of a holder and where its hands are
+    fn held(world: &mut World, holder: Entity) -> (Point, Point)
+    {
+
+        let entry = world.entry(holder).unwrap();
+        let velocity = entry.get_component::<Velocity>().unwrap();
+
+        return (Point { x: velocity.x, y: velocity.y }, hands(entry.get_component::<DynamicBody>().unwrap()));
+
+    }
+
+    #[test]
+    fn rope_grip()
+    {
+
+        let mut world = World::default();
+        let entity = world.push((0,));
+
+        let mut rope = Rope::new(Point { x: 0.0, y: 0.0 }, 100.0, 10);
+
+        //Settles hanging straight down, stretching only a little under its own weight
+        for _ in 0..100
+        {
+
+            rope.simulate(0.3, &[]);
+
+        }
+
+        assert!((rope.points[10].y - 100.0).abs() < 2.0);
+        assert_eq!(rope.nearest(Point { x: 3.0, y: 52.0 }, 5.0).map(|(index, _)| { return index; }), Some(5));
+        assert_eq!(rope.nearest(Point { x: 0.0, y: 0.0 }, 5.0), None);
+
+        //Climbing moves a whole segment at a time and never reaches the anchor or goes past the end
+        let mut grip = RopeGrip::new(entity, 5, Point { x: 0.0, y: 0.0 });
+
+        grip.climb(&rope, -15.0);
+
+        assert_eq!(grip.index, 4);
+
+        grip.climb(&rope, -100.0);
+
+        assert_eq!(grip.index, 1);
+
+        grip.climb(&rope, 200.0);
+
+        assert_eq!(grip.index, 10);
+
+    }
+
+    #[test]
+    fn holder_velocity()
+    {
+
+        let mut world = World::default();
+        let (mut resources, mut schedule) = simulation();
+
+        let (rope, holder) = swinger(&mut world);
+
+        schedule.execute(&mut world, &mut resources);
+
+        //The holder is given the velocity which takes its hands to its point on the rope, which carries its momentum into the swing
+        let point = world.entry(rope).unwrap().get_component::<Rope>().unwrap().points[10];
+        let (velocity, grip) = held(&mut world, holder);
+
+        assert!(velocity.x > 0.0);
+        assert!(distance(grip, point) < 0.01);
+
+        //Instead of falling, it swings at the end of the rope, which only stretches a little
+        for _ in 0..60
+        {
+
+            schedule.execute(&mut world, &mut resources);
+
+        }
+
+        let (_, grip) = held(&mut world, holder);
+
+        assert!(distance(grip, Point { x: 0.0, y: 0.0 }) < 110.0);
+
+    }
+
+    #[test]
+    fn platform_anchor()
+    {
+
+        let mut world = World::default();
+        let (mut resources, mut schedule) = simulation();
+
+        let waypoints = [Waypoint { point: Point { x: 0.0, y: 0.0 }, speed: 100.0, easing: None }, Waypoint { point: Point { x: 200.0, y: 50.0 }, speed: 100.0, easing: None }];
+        let platform = world.push((Kinematic::from_waypoints(&waypoints, false).with_motion(Motion::PingPong, 0, true), StaticBody { body: Rect { x: 0.0, y: 0.0, width: 100.0, height: 20.0 } }));
+
+        let offset = Point { x: 50.0, y: 20.0 };
+        let rope = world.push((Rope::new(offset, 100.0, 10).with_platform(platform, offset),));
+
+        for _ in 0..30
+        {
+
+            schedule.execute(&mut world, &mut resources);
+
+        }
+
+        //The anchor, and the top of the rope with it, follow the platform
+        let body = world.entry(platform).unwrap().get_component::<StaticBody>().unwrap().body;
+        let entry = world.entry(rope).unwrap();
+        let rope = entry.get_component::<Rope>().unwrap();
+
+        assert!(body.x > 0.0);
+        assert_eq!(rope.anchor, Point { x: body.x + offset.x, y: body.y + offset.y });
+        assert_eq!(rope.points[0], rope.anchor);
+
+    }
+
+    #[test]
+    fn release_momentum()
+    {
+
+        let mut world = World::default();
+        let (mut resources, mut schedule) = simulation();
+
+        let (_, holder) = swinger(&mut world);
+
+        for _ in 0..10
+        {
+
+            schedule.execute(&mut world, &mut resources);
+
+        }
+
+        let (swing, _) = held(&mut world, holder);
+
+        //Letting go keeps the swing's velocity, with only gravity added to it
+        world.entry(holder).unwrap().remove_component::<RopeGrip>();
+        schedule.execute(&mut world, &mut resources);
+
+        let (velocity, _) = held(&mut world, holder);
+
+        assert!(swing.x > 0.0);
+        assert_eq!(velocity.x, swing.x);
+        assert!((velocity.y - (swing.y + 0.32)).abs() < 0.001);
+
+    }
+
+}
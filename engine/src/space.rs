@@ -110,6 +110,7 @@ impl Sub for Point
 
 }
 
+#[derive(Clone, Copy, Debug)]
 pub struct Segment
 {
 
@@ -190,9 +191,42 @@ impl Segment
 
 	}
 
+	pub fn length(&self) -> f32
+	{
+
+		let run = self.end - self.start;
+
+		return run.dot(run).sqrt();
+
+	}
+
+	//A segment is a degenerate polygon, so its normal and the rectangle's axes are all SAT needs to test
+	fn to_polygon(&self) -> Polygon
+	{
+
+		let mut vertices: Vec<Point> = Vec::new();
+
+		vertices.push(self.start);
+		vertices.push(self.end - self.start);
+		vertices.push(self.start - self.end);
+
+		return Polygon { vertices };
+
+	}
+
+	pub fn intersects_rectangle(&self, rectangle: &Rect) -> bool
+	{
+
+		let segment = self.to_polygon();
+		let rect = rectangle.to_polygon();
+
+		return Polygon::sat(&segment, &rect);
+
+	}
+
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
 pub struct Rect
 {
 
@@ -226,6 +260,25 @@ impl Rect
 
         return self.y + self.height;
 
+    }
+
+    //Grow the rect by margin on every side
+    pub fn expand(&self, margin: f32) -> Rect
+    {
+
+        return Rect { x: self.x - margin, y: self.y - margin, width: self.width + 2.0 * margin, height: self.height + 2.0 * margin };
+
+    }
+
+    //Smallest rect containing both rects
+    pub fn union(r1: &Rect, r2: &Rect) -> Rect
+    {
+
+        let x = r1.x.min(r2.x);
+        let y = r1.y.min(r2.y);
+
+        return Rect { x, y, width: r1.right().max(r2.right()) - x, height: r1.bottom().max(r2.bottom()) - y };
+
     }
 
 	pub fn to_polygon(&self) -> Polygon
@@ -360,6 +413,65 @@ impl Rect
 
     }
 
+    //Time of impact, as a fraction of the move, of r1 moving by (vx, vy) into stationary r2, along with the normal of the face
+    //of r2 which was hit. Rectangles which already overlap at the start of the move are left to collides
+    pub fn sweep(r1: &Rect, r2: &Rect, (vx, vy): (f32, f32)) -> Option<(f32, (f32, f32))>
+    {
+
+        let (x_entry, x_exit) = Rect::sweep_axis((r1.x, r1.right()), (r2.x, r2.right()), vx)?;
+        let (y_entry, y_exit) = Rect::sweep_axis((r1.y, r1.bottom()), (r2.y, r2.bottom()), vy)?;
+
+        let entry = x_entry.max(y_entry);
+        let exit = x_exit.min(y_exit);
+
+        if entry >= exit || !(0.0..=1.0).contains(&entry)
+        {
+
+            return None;
+
+        }
+
+        //Corners count as landing on (or hitting the underside of) r2 rather than hitting its side
+        if x_entry > y_entry
+        {
+
+            return Some((entry, (-vx.signum(), 0.0)));
+
+        }
+
+        return Some((entry, (0.0, -vy.signum())));
+
+    }
+
+    //Fractions of the move at which the projections onto one axis start and stop overlapping
+    fn sweep_axis((start1, end1): (f32, f32), (start2, end2): (f32, f32), v: f32) -> Option<(f32, f32)>
+    {
+
+        if v.abs() < FLOATING_POINT_ERROR
+        {
+
+            if start1 < end2 && end1 > start2
+            {
+
+                return Some((f32::NEG_INFINITY, f32::INFINITY));
+
+            }
+
+            return None;
+
+        }
+
+        if v > 0.0
+        {
+
+            return Some(((start2 - end1) / v, (end2 - start1) / v));
+
+        }
+
+        return Some(((end2 - start1) / v, (start2 - end1) / v));
+
+    }
+
 }
 
 #[derive(Debug)]
@@ -406,6 +518,7 @@ impl Triangle
 	
 }
 
+#[derive(Clone)]
 pub struct Polygon
 {
 
@@ -417,6 +530,81 @@ pub struct Polygon
 impl Polygon
 {
 
+	//Build a polygon from its vertices in world coordinates, in either winding order
+	pub fn from_points(points: &[Point]) -> Polygon
+	{
+
+		if points.len() < 3
+		{
+
+			panic!("Cannot create a polygon with fewer than three vertices.");
+
+		}
+
+		let mut vertices: Vec<Point> = Vec::new();
+
+		vertices.push(points[0]);
+
+		for i in 0..points.len()
+		{
+
+			vertices.push(points[(i + 1) % points.len()] - points[i]);
+
+		}
+
+		//Collisions are resolved with the separating axis theorem, which only works for convex shapes. Every corner has to
+		//turn the same way, and all the turns together have to go round exactly once so that self crossing stars are caught
+		let edges = &vertices[1..];
+		let mut turns = 0.0;
+		let mut winding = 0.0;
+
+		for i in 0..edges.len()
+		{
+
+			let (a, b) = (edges[i], edges[(i + 1) % edges.len()]);
+			let cross = a.x * b.y - a.y * b.x;
+
+			if cross.abs() < FLOATING_POINT_ERROR
+			{
+
+				continue;
+
+			}
+
+			if cross * winding < 0.0
+			{
+
+				panic!("Cannot create a polygon which is not convex.");
+
+			}
+
+			winding = cross.signum();
+			turns += cross.atan2(a.dot(b));
+
+		}
+
+		if (turns.abs() - 2.0 * std::f32::consts::PI).abs() > 0.01
+		{
+
+			panic!("Cannot create a polygon which is not convex.");
+
+		}
+
+		return Polygon { vertices };
+
+	}
+
+	//Smallest rectangle containing the polygon
+	pub fn bounds(&self) -> Rect
+	{
+
+		let (left, right) = self.project(Point { x: 1.0, y: 0.0 });
+		let (top, bottom) = self.project(Point { x: 0.0, y: 1.0 });
+
+		return Rect { x: left, y: top, width: right - left, height: bottom - top };
+
+	}
+
 	//Project the polygon along a vector and return its (min, max)
 	pub fn project(&self, vector: Point) -> (f32, f32)
 	{
@@ -498,6 +686,55 @@ impl Polygon
 
 	}
 
+	//Minimum translation vector which moves p1 out of p2, or None if they do not intersect. Same caveats as sat
+	pub fn mtv(p1: &Polygon, p2: &Polygon) -> Option<Point>
+	{
+
+		let mut best: Option<(Point, f32)> = None;
+
+		for v in p1.vertices.iter().skip(1).chain(p2.vertices.iter().skip(1))
+		{
+
+			let length = v.dot(*v).sqrt();
+
+			if length < FLOATING_POINT_ERROR
+			{
+
+				continue;
+
+			}
+
+			let normal = Point { x: -v.y / length, y: v.x / length };
+
+			let (min1, max1) = p1.project(normal);
+			let (min2, max2) = p2.project(normal);
+
+			if min1 >= max2 || min2 >= max1
+			{
+
+				return None;
+
+			}
+
+			//Push p1 whichever way along the axis is shorter
+			let forward = max2 - min1;
+			let backward = max1 - min2;
+
+			let (axis, overlap) = if forward < backward { (normal, forward) } else { (normal.scale(-1.0), backward) };
+
+			if best.is_none_or(|(_, shortest)| { return overlap < shortest; })
+			{
+
+				best = Some((axis, overlap));
+
+			}
+
+		}
+
+		return best.map(|(axis, overlap)| { return axis.scale(overlap); });
+
+	}
+
 }
 
 #[cfg(test)]
@@ -581,6 +818,70 @@ mod tests
 
 	}
 
+	#[test]
+	fn minimum_translation()
+	{
+
+		let slope = Polygon::from_points(&[Point { x: 0.0, y: 20.0 }, Point { x: 20.0, y: 0.0 }, Point { x: 20.0, y: 20.0 }]);
+
+		let bounds = slope.bounds();
+
+		assert_eq!((bounds.x, bounds.y, bounds.width, bounds.height), (0.0, 0.0, 20.0, 20.0));
+
+		let sunk = Rect { x: 8.0, y: 6.0, width: 4.0, height: 10.0 }.to_polygon();
+		let clear = Rect { x: 0.0, y: 0.0, width: 4.0, height: 10.0 }.to_polygon();
+
+		let mtv = Polygon::mtv(&sunk, &slope).unwrap();
+
+		assert!((mtv.x + 4.0).abs() < FLOATING_POINT_ERROR);
+		assert!((mtv.y + 4.0).abs() < FLOATING_POINT_ERROR);
+
+		assert!(Polygon::mtv(&clear, &slope).is_none());
+
+	}
+
+	#[test]
+	#[should_panic(expected = "not convex")]
+	fn concave_polygon()
+	{
+
+		Polygon::from_points(&[Point { x: 0.0, y: 0.0 }, Point { x: 20.0, y: 0.0 }, Point { x: 10.0, y: 5.0 }, Point { x: 20.0, y: 20.0 }, Point { x: 0.0, y: 20.0 }]);
+
+	}
+
+	#[test]
+	#[should_panic(expected = "not convex")]
+	fn crossed_polygon()
+	{
+
+		Polygon::from_points(&[Point { x: 0.0, y: 0.0 }, Point { x: 20.0, y: 20.0 }, Point { x: 20.0, y: 0.0 }, Point { x: 0.0, y: 20.0 }]);
+
+	}
+
+	#[test]
+	fn swept_rectangle()
+	{
+
+		let body = Rect { x: 0.0, y: 0.0, width: 10.0, height: 10.0 };
+		let platform = Rect { x: -20.0, y: 30.0, width: 50.0, height: 5.0 };
+		let wall = Rect { x: 15.0, y: -50.0, width: 5.0, height: 100.0 };
+
+		//Falling far enough in one step to pass straight through the platform
+		assert_eq!(Rect::sweep(&body, &platform, (0.0, 40.0)), Some((0.5, (0.0, -1.0))));
+		assert_eq!(Rect::sweep(&body, &platform, (0.0, 10.0)), None);
+
+		assert_eq!(Rect::sweep(&body, &wall, (10.0, 5.0)), Some((0.5, (-1.0, 0.0))));
+		assert_eq!(Rect::sweep(&body, &wall, (-10.0, 5.0)), None);
+
+		//Resting contact is a hit at the very start of the move, overlap is not
+		let resting = Rect { x: 0.0, y: 20.0, width: 10.0, height: 10.0 };
+		let sunk = Rect { x: 0.0, y: 21.0, width: 10.0, height: 10.0 };
+
+		assert_eq!(Rect::sweep(&resting, &platform, (3.0, 0.5)), Some((0.0, (0.0, -1.0))));
+		assert_eq!(Rect::sweep(&sunk, &platform, (3.0, 0.5)), None);
+
+	}
+
 	#[test]
 	fn raycast()
 	{
@@ -607,4 +908,24 @@ mod tests
 
 	}
 
+	#[test]
+	fn segment_rectangle_intersection()
+	{
+
+		let rect = Rect { x: 10.0, y: 10.0, width: 20.0, height: 10.0 };
+
+		let crossing = Segment::new(Point { x: 0.0, y: 15.0 }, Point { x: 40.0, y: 15.0 });
+		let inside = Segment::new(Point { x: 12.0, y: 12.0 }, Point { x: 15.0, y: 18.0 });
+		let diagonal = Segment::new(Point { x: 0.0, y: 0.0 }, Point { x: 40.0, y: 40.0 });
+		let short = Segment::new(Point { x: 0.0, y: 15.0 }, Point { x: 9.0, y: 15.0 });
+		let corner = Segment::new(Point { x: 25.0, y: 0.0 }, Point { x: 40.0, y: 15.0 });
+
+		assert!(crossing.intersects_rectangle(&rect));
+		assert!(inside.intersects_rectangle(&rect));
+		assert!(diagonal.intersects_rectangle(&rect));
+		assert!(!short.intersects_rectangle(&rect));
+		assert!(!corner.intersects_rectangle(&rect));
+
+	}
+
 }
@@ -6,16 +6,23 @@ use legion::*;
 
 use std::fs::File;
 
+use engine::alarm::{Cover, NoiseData, Noisy};
 use engine::codes::{Codes, ConsumeWatcher, Watcher, WatcherData};
-use engine::space::Rect;
-use engine::physics::{Kinematic, OneWayBody, StaticBody};
+use engine::navigation::{NavAgent, NavGraph};
+use engine::rope::{Rope, RopeData};
+use engine::space::{Point, Polygon, Rect};
+use engine::physics::{Climbable, Contacts, Crush, DynamicBody, Fluid, Gravity, GravityScale, GravityZone, HasGravity, InteractsWithOneWay, Kinematic, Layers, Material, Motion, NoWallJump, OneWayBody, PolygonBody, Solid, StaticBody, Velocity, Waypoint, LAYER_ALL, LAYER_CRATE};
 
 #[derive(Deserialize)]
 struct Body
 {
 
     body: Rect,
-    oneway: bool
+    oneway: bool,
+    layers: Option<Vec<String>>,
+    mask: Option<Vec<String>>,
+    surface: Option<Material>,
+    wall_jump: Option<bool>
 
 }
 
@@ -24,19 +31,69 @@ struct Platform
 {
 
     body: Body,
-    x_param: String,
+    #[serde(default)]
+    x_param: String, //Left out for platforms following waypoints
+    #[serde(default)]
     y_param: String,
+    #[serde(default)]
     max: i32,
+    waypoints: Option<Vec<Waypoint>>, //Used instead of the parametrized path when given
+    motion: Option<Motion>,
+    pause: Option<i32>,
+    moving: Option<bool>,
+    crush: Option<Crush>,
+    ropes: Option<Vec<RopeData>>, //Anchored relative to the platform's top left corner
     watchers: Vec<WatcherData>
 
 } 
 
+#[derive(Deserialize)]
+struct PolygonData
+{
+
+    points: Vec<Point>,
+    oneway: Option<bool>,
+    layers: Option<Vec<String>>,
+    mask: Option<Vec<String>>,
+    surface: Option<Material>,
+    wall_jump: Option<bool>
+
+}
+
+//Crates are on the crate layer and collide with everything unless told otherwise
+#[derive(Deserialize)]
+struct Crate
+{
+
+    body: Rect,
+    mass: f32,
+    gravity: Option<GravityScale>,
+    layers: Option<Vec<String>>,
+    mask: Option<Vec<String>>,
+    noise: Option<NoiseData> //Landing hard enough is heard by nearby guards
+
+}
+
 #[derive(Deserialize)]
 struct CollisionMap
 {
 
     bodies: Vec<Body>,
-    platforms: Vec<Platform>
+    platforms: Vec<Platform>,
+    #[serde(default)]
+    cover: Vec<Rect>,
+    #[serde(default)]
+    ladders: Vec<Rect>,
+    #[serde(default)]
+    fluids: Vec<Fluid>,
+    #[serde(default)]
+    gravity_zones: Vec<GravityZone>,
+    #[serde(default)]
+    ropes: Vec<RopeData>,
+    #[serde(default)]
+    polygons: Vec<PolygonData>,
+    #[serde(default)]
+    crates: Vec<Crate>
 
 }
 
@@ -55,15 +112,19 @@ pub fn load_collision(world: &mut World, codes: &mut Codes, file: &str, director
             for body in m.bodies
             {
 
-                if body.oneway
+                let layers = Layers::from_names(&body.layers, &body.mask);
+
+                let entity = if body.oneway
                 {
 
                     world.push(
                     (
                            
                            OneWayBody { body: body.body },
+                           layers,
+                           body.surface.unwrap_or_default()
 
-                    ));
+                    ))
 
                 }
                 else
@@ -73,22 +134,180 @@ pub fn load_collision(world: &mut World, codes: &mut Codes, file: &str, director
                     (
 
                             StaticBody { body: body.body },
+                            layers,
+                            body.surface.unwrap_or_default()
+
+                    ))
 
-                    ));
+                };
+
+                if body.wall_jump == Some(false)
+                {
+
+                    world.entry(entity).unwrap().add_component(NoWallJump {});
 
                 }
 
             }
 
+            for polygon in m.polygons
+            {
+
+                let layers = Layers::from_names(&polygon.layers, &polygon.mask);
+
+                let entity = world.push(
+                (
+
+                    PolygonBody::new(polygon.points).with_oneway(polygon.oneway.unwrap_or(false)),
+                    layers,
+                    polygon.surface.unwrap_or_default()
+
+                ));
+
+                if polygon.wall_jump == Some(false)
+                {
+
+                    world.entry(entity).unwrap().add_component(NoWallJump {});
+
+                }
+
+            }
+
+            for data in m.crates
+            {
+
+                let entity = world.push(
+                (
+
+                    DynamicBody::new(data.body.x, data.body.y, data.body.width, data.body.height),
+                    Velocity::new(0.0, 0.0),
+                    HasGravity {},
+                    InteractsWithOneWay {},
+                    Solid { mass: data.mass, pushable: true },
+                    Layers::new(LAYER_CRATE, LAYER_ALL).with_names(&data.layers, &data.mask),
+                    Contacts::new()
+
+                ));
+
+                if let Some(gravity) = data.gravity
+                {
+
+                    world.entry(entity).unwrap().add_component(gravity);
+
+                }
+
+                if let Some(noise) = &data.noise
+                {
+
+                    world.entry(entity).unwrap().add_component(Noisy::new(noise, codes));
+
+                }
+
+            }
+
+            for rect in m.cover
+            {
+
+                world.push(
+                (
+
+                    Cover { rect },
+
+                ));
+
+            }
+
+            for rect in m.ladders
+            {
+
+                world.push(
+                (
+
+                    Climbable { rect },
+
+                ));
+
+            }
+
+            for fluid in m.fluids
+            {
+
+                world.push(
+                (
+
+                    fluid,
+
+                ));
+
+            }
+
+            for zone in m.gravity_zones
+            {
+
+                world.push(
+                (
+
+                    zone,
+
+                ));
+
+            }
+
+            for data in m.ropes
+            {
+
+                world.push(
+                (
+
+                    Rope::from_data(&data),
+
+                ));
+
+            }
+
             for platform in m.platforms
             {
 
-                let x_param = Parametrizer::new(&platform.x_param).unwrap();
-                let y_param = Parametrizer::new(&platform.y_param).unwrap();
+                let motion = platform.motion.unwrap_or(Motion::Toggle);
+
+                let kinematic = match &platform.waypoints
+                {
+
+                    Some(waypoints) => Kinematic::from_waypoints(waypoints, motion == Motion::Loop),
+                    None =>
+                    {
+
+                        if platform.x_param.is_empty() || platform.y_param.is_empty()
+                        {
 
-                let entity: Entity = world.push((Kinematic::new(x_param, y_param, platform.max), ));
+                            panic!("Moving platforms need either waypoints or both an x_param and a y_param.");
+
+                        }
+
+                        let x_param = Parametrizer::new(&platform.x_param).unwrap();
+                        let y_param = Parametrizer::new(&platform.y_param).unwrap();
+
+                        Kinematic::new(x_param, y_param, platform.max)
+
+                    }
+
+                };
+
+                let kinematic = kinematic.with_motion(motion, platform.pause.unwrap_or(0), platform.moving.unwrap_or(false)).with_crush(platform.crush.unwrap_or(Crush::Push));
+
+                let entity: Entity = world.push((kinematic, ));
                 let mut entry = world.entry(entity).unwrap();
 
+                entry.add_component(Layers::from_names(&platform.body.layers, &platform.body.mask));
+                entry.add_component(platform.body.surface.unwrap_or_default());
+
+                if platform.body.wall_jump == Some(false)
+                {
+
+                    entry.add_component(NoWallJump {});
+
+                }
+
                 if platform.body.oneway
                 {
 
@@ -102,6 +321,15 @@ pub fn load_collision(world: &mut World, codes: &mut Codes, file: &str, director
 
                 }
 
+                let ropes: Vec<Rope> = platform.ropes.iter().flatten().map(|data|
+                {
+
+                    let anchor = Point { x: platform.body.body.x + data.anchor.x, y: platform.body.body.y + data.anchor.y };
+
+                    return Rope::from_data(&RopeData { anchor, ..*data }).with_platform(entity, data.anchor);
+
+                }).collect();
+
                 for data in platform.watchers.iter()
                 {
 
@@ -120,6 +348,8 @@ pub fn load_collision(world: &mut World, codes: &mut Codes, file: &str, director
 
                 }
 
+                world.extend(ropes.into_iter().map(|rope| { return (rope,); }));
+
             }
 
         }
@@ -129,3 +359,24 @@ pub fn load_collision(world: &mut World, codes: &mut Codes, file: &str, director
     }
 
 }
+
+//Moving platforms are left out, since the graph is only built once when the level loads. So are bodies agents on the given
+//layers pass straight through
+pub fn build_navigation(world: &World, agent: &NavAgent, layers: &Layers, gravity: &Gravity, step: i32) -> NavGraph
+{
+
+    let mut static_query = <(&StaticBody, Option<&Layers>)>::query().filter(!component::<Kinematic>());
+    let solids: Vec<Rect> = static_query.iter(world).filter(|(_, l)| { return Layers::interact(layers, &Layers::or_default(*l)); }).map(|(s, _)| { return s.body; }).collect();
+
+    let mut oneway_query = <(&OneWayBody, Option<&Layers>)>::query().filter(!component::<Kinematic>());
+    let oneways: Vec<Rect> = oneway_query.iter(world).filter(|(_, l)| { return Layers::interact(layers, &Layers::or_default(*l)); }).map(|(o, _)| { return o.body; }).collect();
+
+    let mut polygon_query = <(&PolygonBody, Option<&Layers>)>::query();
+    let (oneway_polygons, polygons): (Vec<&PolygonBody>, Vec<&PolygonBody>) = polygon_query.iter(world).filter(|(_, l)| { return Layers::interact(layers, &Layers::or_default(*l)); }).map(|(p, _)| { return p; }).partition(|p| { return p.oneway; });
+
+    let polygons: Vec<Polygon> = polygons.iter().map(|p| { return Polygon::from_points(&p.points); }).collect();
+    let oneway_polygons: Vec<Polygon> = oneway_polygons.iter().map(|p| { return Polygon::from_points(&p.points); }).collect();
+
+    return NavGraph::new(&solids, &oneways, &polygons, &oneway_polygons, agent, gravity, step);
+
+}
@@ -6,7 +6,8 @@ use legion::systems::Builder;
 
 use std::fs::File;
 
-use engine::space::Rect;
+use engine::alarm::{Disturbance, Suspicious, Tripwire};
+use engine::space::{Point, Rect, Segment};
 use engine::codes::Codes;
 
 pub struct Trigger
@@ -18,11 +19,35 @@ pub struct Trigger
     
 }
 
+pub struct HideZone
+{
+
+    pub rect: Rect
+
+}
+
+pub struct Terminal
+{
+
+    pub rect: Rect,
+    pub observer: String,
+    pub duration: i32
+
+}
+
 #[derive(Deserialize)]
 struct EventMap
 {
 
-    triggers: Vec<TriggerData>
+    triggers: Vec<TriggerData>,
+    #[serde(default)]
+    hides: Vec<Rect>,
+    #[serde(default)]
+    terminals: Vec<TerminalData>,
+    #[serde(default)]
+    disturbances: Vec<DisturbanceData>,
+    #[serde(default)]
+    tripwires: Vec<TripwireData>
 
 }
 
@@ -36,6 +61,40 @@ struct TriggerData
 
 }
 
+#[derive(Deserialize)]
+struct TerminalData
+{
+
+    rect: Rect,
+    observer: String,
+    duration: i32
+
+}
+
+#[derive(Deserialize)]
+struct DisturbanceData
+{
+
+    rect: Rect,
+    trigger: String,
+    code: String,
+    priority: i32
+
+}
+
+#[derive(Deserialize)]
+struct TripwireData
+{
+
+    beams: Vec<(Point, Point)>,
+    code: String,
+    disable: Option<String>,
+    on: i32,
+    off: i32,
+    offset: i32
+
+}
+
 pub fn load_events(world: &mut World, codes: &mut Codes, file: &str, directory: &str)
 {
 
@@ -60,6 +119,58 @@ pub fn load_events(world: &mut World, codes: &mut Codes, file: &str, directory:
 
             }
 
+            for rect in e.hides
+            {
+
+                world.push(
+                (
+
+                    HideZone { rect },
+
+                ));
+
+            }
+
+            for terminal in e.terminals
+            {
+
+                world.push(
+                (
+
+                    Terminal { rect: terminal.rect, observer: terminal.observer, duration: terminal.duration },
+
+                ));
+
+            }
+
+            for disturbance in e.disturbances
+            {
+
+                world.push(
+                (
+
+                    Disturbance::new(disturbance.rect, codes.get_code(&disturbance.trigger)),
+                    Suspicious::with_priority(codes.get_code(&disturbance.code), disturbance.priority)
+
+                ));
+
+            }
+
+            for tripwire in e.tripwires
+            {
+
+                let beams = tripwire.beams.iter().map(|(start, end)| { return Segment::new(*start, *end); }).collect();
+                let disable = tripwire.disable.map(|name| { return codes.get_code(&name); });
+
+                world.push(
+                (
+
+                    Tripwire::new(beams, codes.get_code(&tripwire.code), disable, tripwire.on, tripwire.off, tripwire.offset),
+
+                ));
+
+            }
+
         }
         Err(e) => panic!("Unable to parse event map RON file {} with error {}", file, e) 
 
@@ -0,0 +1,562 @@
+use serde::Deserialize;
+
+use legion::*;
+use legion::world::SubWorld;
+use legion::systems::{Builder, CommandBuffer};
+
+use engine::alarm::{Cone, Disturbance, Observer, ObserverData, Observers, Radio, Suspicious, INTRUDER_PRIORITY};
+use engine::codes::Codes;
+use engine::game::Timestep;
+use engine::navigation::{NavGraph, NavKind, NavStep};
+use engine::physics::{Broadphase, Contacts, DynamicBody, HasGravity, InteractsWithOneWay, Layers, ResetOneWayInteraction, Solid, Velocity, LAYER_ALL, LAYER_GUARD};
+use engine::space::{Point, Rect};
+
+//How far ahead of itself a guard looks for walls and ledges
+const PROBE_DISTANCE: f32 = 2.0;
+const LEDGE_DEPTH: f32 = 4.0;
+
+//Guards cannot be pushed around, so their mass only matters to anything they push
+const GUARD_MASS: f32 = 2.0;
+
+//How often a guard following a route recomputes it, in milliseconds
+const REPLAN_TIME: i32 = 500;
+
+//A disabled guard is more worrying than a door left open, but not worth dropping an intruder for
+const DOWNED_PRIORITY: i32 = 75;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum GuardState
+{
+
+	Patrol,
+	Investigate,
+	Chase,
+	Return
+
+}
+
+pub struct Guard
+{
+
+	waypoints: Vec<Point>,
+	current: usize,
+	patrol_speed: f32,
+	chase_speed: f32,
+	pause: i32,
+	search: i32,
+	alert_time: i32,
+	alert: u128,
+	suspicion: i32,
+	wait: i32,
+	last_seen: Option<Point>,
+	lead: bool, //Whether last_seen is a fresh sighting or report that has not been followed up yet
+	path: Vec<NavStep>,
+	replan: i32,
+	pub state: GuardState
+
+}
+
+#[derive(Deserialize)]
+pub struct GuardData
+{
+
+	body: Rect,
+	waypoints: Vec<Point>,
+	patrol_speed: f32,
+	chase_speed: f32,
+	pause: i32,
+	search: i32,
+	alert_time: i32,
+	alert: String,
+	observer: ObserverData,
+	downed: Option<String> //Code other observers raise on seeing this guard while it is disabled
+
+}
+
+//Everything a guard needs to get about this step: where it is, how far it moves, the graph it routes through and the level's bodies
+struct Pathing<'a>
+{
+
+	graph: &'a NavGraph,
+	broadphase: &'a Broadphase,
+	body: Rect,
+	speed: f32
+
+}
+
+impl Guard
+{
+
+	pub fn new(waypoints: Vec<Point>, patrol_speed: f32, chase_speed: f32, pause: i32, search: i32, alert_time: i32, alert: u128) -> Guard
+	{
+
+		if waypoints.is_empty()
+		{
+
+			panic!("Cannot create a guard without any patrol waypoints.");
+
+		}
+
+		return Guard { waypoints, current: 0, patrol_speed, chase_speed, pause, search, alert_time, alert, suspicion: 0, wait: 0, last_seen: None, lead: false, path: Vec::new(), replan: 0, state: GuardState::Patrol };
+
+	}
+
+	pub fn layers() -> Layers
+	{
+
+		return Layers::new(LAYER_GUARD, LAYER_ALL);
+
+	}
+
+	fn change_state(&mut self, state: GuardState)
+	{
+
+		self.state = state;
+		self.wait = 0;
+
+		self.path.clear();
+		self.replan = 0;
+
+	}
+
+	//Where the guard is currently headed and how fast it should get there
+	fn target(&self) -> (Point, f32)
+	{
+
+		match self.state
+		{
+
+			GuardState::Patrol | GuardState::Return => return (self.waypoints[self.current], self.patrol_speed),
+			GuardState::Investigate => return (self.last_seen.unwrap_or(self.waypoints[self.current]), self.patrol_speed),
+			GuardState::Chase => return (self.last_seen.unwrap_or(self.waypoints[self.current]), self.chase_speed)
+
+		}
+
+	}
+
+	//State changes driven by what the guard can see, what it hears over the radio and the level's alarm codes. The alarm only
+	//sends a guard chasing after a lead it has not followed up yet, so reaching an empty last known position ends the chase
+	fn perceive(&mut self, sighting: Option<Point>, intruder: bool, report: Option<Point>, alarm: bool, step: i32)
+	{
+
+		match sighting
+		{
+
+			Some(point) =>
+			{
+
+				self.last_seen = Some(point);
+				self.lead = true;
+
+				//Disturbances are worth a look, but only an intruder is worth chasing
+				if intruder
+				{
+
+					self.suspicion += step;
+
+				}
+
+			},
+			None => self.suspicion = (self.suspicion - step).max(0)
+
+		}
+
+		if let Some(point) = report
+		{
+
+			self.last_seen = Some(point);
+			self.lead = true;
+
+		}
+
+		let alerted = alarm && self.lead;
+
+		match self.state
+		{
+
+			GuardState::Patrol | GuardState::Return =>
+			{
+
+				if alerted
+				{
+
+					self.change_state(GuardState::Chase);
+
+				}
+				else if sighting.is_some()
+				{
+
+					self.change_state(GuardState::Investigate);
+
+				}
+
+			},
+			GuardState::Investigate =>
+			{
+
+				if alerted || self.suspicion >= self.alert_time
+				{
+
+					self.change_state(GuardState::Chase);
+
+				}
+
+			},
+			GuardState::Chase => {}
+
+		}
+
+	}
+
+	//Called each step the guard is where it was heading
+	fn arrive(&mut self, sighting: bool, step: i32)
+	{
+
+		match self.state
+		{
+
+			GuardState::Patrol =>
+			{
+
+				self.wait += step;
+
+				if self.wait >= self.pause
+				{
+
+					self.next_waypoint();
+					self.wait = 0;
+
+				}
+
+			},
+			GuardState::Investigate =>
+			{
+
+				self.wait += step;
+
+				if self.wait >= self.search && !sighting
+				{
+
+					self.last_seen = None;
+					self.change_state(GuardState::Return);
+
+				}
+
+			},
+			GuardState::Chase =>
+			{
+
+				//Lost track of the target, so search the last place it was seen
+				if !sighting
+				{
+
+					self.lead = false;
+					self.change_state(GuardState::Investigate);
+
+				}
+
+			},
+			GuardState::Return => self.change_state(GuardState::Patrol)
+
+		}
+
+	}
+
+	fn next_waypoint(&mut self)
+	{
+
+		self.current = (self.current + 1) % self.waypoints.len();
+
+	}
+
+	//Walls in front of the guard, or a missing floor beneath its leading edge, block it
+	fn blocked(body: &Rect, direction: f32, broadphase: &Broadphase) -> bool
+	{
+
+		let wall = Rect { x: body.x + direction * PROBE_DISTANCE, y: body.y, width: body.width, height: body.height };
+
+		let layers = Guard::layers();
+
+		if broadphase.statics(&wall, &layers).iter().any(|(_, body)| { return Rect::intersects(&wall, body); })
+		{
+
+			return true;
+
+		}
+
+		let ledge = Rect { x: if direction > 0.0 { body.right() } else { body.x - PROBE_DISTANCE }, y: body.bottom(), width: PROBE_DISTANCE, height: LEDGE_DEPTH };
+
+		let floor = broadphase.statics(&ledge, &layers).iter().chain(broadphase.oneways(&ledge, &layers).iter()).any(|(_, body)| { return Rect::intersects(&ledge, body); });
+
+		return !floor;
+
+	}
+
+	//Walk straight at a point, stopping at walls and ledges. Returns true once there is nowhere further to go
+	fn walk(x: f32, pathing: &Pathing, velocity: &mut Velocity) -> bool
+	{
+
+		let body = &pathing.body;
+
+		let distance = x - (body.x + body.width * 0.5);
+		let direction = if distance < 0.0 { -1.0 } else { 1.0 };
+
+		if distance.abs() <= pathing.speed || Guard::blocked(body, direction, pathing.broadphase)
+		{
+
+			return true;
+
+		}
+
+		velocity.x = direction * pathing.speed;
+
+		return false;
+
+	}
+
+	//Follow the current route towards the target, falling back to walking straight at it when no route is known.
+	//Returns true once the guard has arrived
+	fn route(&mut self, target: Point, pathing: &Pathing, velocity: &mut Velocity, cmd: &mut CommandBuffer, entity: Entity) -> bool
+	{
+
+		let (body, speed) = (&pathing.body, pathing.speed);
+		let centre = body.x + body.width * 0.5;
+
+		if self.replan <= 0
+		{
+
+			self.path = pathing.graph.find_path(Point { x: centre, y: body.bottom() }, target).unwrap_or_default();
+			self.replan = REPLAN_TIME;
+
+		}
+
+		let leg = match self.path.first()
+		{
+
+			Some(leg) => *leg,
+			None => return Guard::walk(target.x, pathing, velocity)
+
+		};
+
+		match leg.kind
+		{
+
+			NavKind::Walk =>
+			{
+
+				if (leg.end.x - centre).abs() <= speed
+				{
+
+					self.path.remove(0);
+
+				}
+				else
+				{
+
+					velocity.x = if leg.end.x < centre { -speed } else { speed };
+
+				}
+
+			},
+			_ =>
+			{
+
+				//Falls start once the body is half over the edge, everything else from the takeoff point itself
+				let takeoff = if leg.kind == NavKind::Fall { leg.start.x - leg.velocity.0.signum() * body.width * 0.5 } else { leg.start.x };
+
+				if (takeoff - centre).abs() <= speed
+				{
+
+					velocity.x = leg.velocity.0;
+					velocity.y = leg.velocity.1;
+
+					if leg.kind == NavKind::DropThrough
+					{
+
+						cmd.remove_component::<InteractsWithOneWay>(entity);
+						cmd.add_component(entity, ResetOneWayInteraction::new(100));
+
+					}
+
+					self.path.remove(0);
+
+				}
+				else
+				{
+
+					velocity.x = if takeoff < centre { -speed } else { speed };
+
+				}
+
+			}
+
+		}
+
+		return false;
+
+	}
+
+}
+
+#[system]
+#[write_component(Guard)]
+#[write_component(Observer)]
+#[read_component(DynamicBody)]
+#[write_component(Velocity)]
+fn guard_think(world: &mut SubWorld, cmd: &mut CommandBuffer, #[resource] step: &Timestep, #[resource] codes: &Codes, #[resource] graph: &NavGraph, #[resource] broadphase: &Broadphase)
+{
+
+	let mut query = <(Entity, &mut Guard, &mut Observer, &DynamicBody, &mut Velocity)>::query();
+
+	for (entity, guard, observer, dynamic, velocity) in query.iter_mut(world)
+	{
+
+		guard.replan -= step.step;
+
+		//Radio reports only say where to look, so they never add to the guard's own suspicion
+		let report = observer.report.take();
+
+		guard.perceive(observer.sighting, observer.priority >= INTRUDER_PRIORITY, report, codes.contains(guard.alert), step.step);
+
+		if dynamic.top_collision != 0
+		{
+
+			continue;
+
+		}
+
+		velocity.x = 0.0;
+
+		let (target, speed) = guard.target();
+		let pathing = Pathing { graph, broadphase, body: dynamic.body, speed: speed * step.step as f32 / 1000.0 };
+
+		//Patrols stick to the stretch of floor between waypoints, everything else routes through the navigation graph
+		let arrived = match guard.state
+		{
+
+			GuardState::Patrol => Guard::walk(target.x, &pathing, velocity),
+			_ => guard.route(target, &pathing, velocity, cmd, *entity)
+
+		};
+
+		if !arrived
+		{
+
+			continue;
+
+		}
+
+		guard.arrive(observer.sighting.is_some(), step.step);
+
+	}
+
+}
+
+pub fn load_guards(world: &mut World, codes: &mut Codes, observers: &mut Observers, guards: Vec<GuardData>)
+{
+
+	for data in guards
+	{
+
+		let location = Point { x: data.body.x, y: data.body.y };
+		let observer = Observer::new(location, data.observer.offset, data.observer.upper, data.observer.lower, codes.get_code(&data.observer.code), data.observer.reaction);
+
+		let entity = world.push(
+		(
+
+			Guard::new(data.waypoints, data.patrol_speed, data.chase_speed, data.pause, data.search, data.alert_time, codes.get_code(&data.alert)),
+			observer,
+			Cone { field: Vec::new() },
+			DynamicBody::new(data.body.x, data.body.y, data.body.width, data.body.height),
+			Velocity::new(0.0, 0.0),
+			HasGravity {},
+			InteractsWithOneWay {},
+			Solid { mass: GUARD_MASS, pushable: false }
+
+		));
+
+		world.entry(entity).unwrap().add_component(Guard::layers());
+		world.entry(entity).unwrap().add_component(Contacts::new());
+
+		if let Some(radio) = &data.observer.radio
+		{
+
+			world.entry(entity).unwrap().add_component(Radio::new(radio, codes));
+
+		}
+
+		if let Some(name) = &data.observer.name
+		{
+
+			observers.insert(name, entity);
+
+		}
+
+		if let Some(code) = &data.downed
+		{
+
+			world.entry(entity).unwrap().add_component(Disturbance::downed());
+			world.entry(entity).unwrap().add_component(Suspicious::with_priority(codes.get_code(code), DOWNED_PRIORITY));
+
+		}
+
+	}
+
+}
+
+pub fn schedule_guard_systems(schedule: &mut Builder)
+{
+
+	schedule.add_system(guard_think_system());
+
+}
+
+#[cfg(test)]
+mod tests
+{
+
+	use super::*;
+
+	#[test]
+	fn chase_ends_in_search()
+	{
+
+		let mut guard = Guard::new(vec![Point { x: 0.0, y: 0.0 }], 50.0, 100.0, 0, 1000, 500, 1);
+		let seen = Point { x: 100.0, y: 0.0 };
+
+		//Seen during an alarm, so straight to chasing
+		guard.perceive(Some(seen), true, None, true, 16);
+
+		assert!(guard.state == GuardState::Chase);
+
+		//Reaching the last known position with nothing in sight drops to searching, and the alarm still being up does not
+		//restart the chase without a new sighting
+		guard.perceive(None, false, None, true, 16);
+		guard.arrive(false, 16);
+
+		assert!(guard.state == GuardState::Investigate);
+
+		for _ in 0..62
+		{
+
+			guard.perceive(None, false, None, true, 16);
+			guard.arrive(false, 16);
+
+			assert!(guard.state == GuardState::Investigate);
+
+		}
+
+		guard.perceive(None, false, None, true, 16);
+		guard.arrive(false, 16);
+
+		assert!(guard.state == GuardState::Return);
+		assert!(guard.last_seen.is_none());
+
+		//A radio report during the alarm is a new lead worth chasing
+		guard.perceive(None, false, Some(seen), true, 16);
+
+		assert!(guard.state == GuardState::Chase);
+
+	}
+
+}
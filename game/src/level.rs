@@ -5,11 +5,20 @@ use legion::*;
 
 use std::fs::File;
 
+use engine::alarm::{AlarmController, AlarmData, Observers};
 use engine::camera::WorldSize;
 use engine::codes::Codes;
+use engine::game::Timestep;
+use engine::navigation::{NavAgent, NavGraph};
+use engine::physics::{Broadphase, Gravity};
 
 use super::collisionmap;
 use super::eventmap;
+use super::eventmap::Terminal;
+use super::guard;
+use super::guard::{Guard, GuardData};
+use super::surveillance;
+use super::surveillance::CameraData;
 
 #[derive(Deserialize)]
 struct Level
@@ -18,7 +27,14 @@ struct Level
     width: f32,
     height: f32,
     collision: (String, String),
-    event: (String, String)
+    event: (String, String),
+    #[serde(default)]
+    guards: Vec<GuardData>,
+    #[serde(default)]
+    cameras: Vec<CameraData>,
+    navigation: Option<NavAgent>, //Only needed by levels with guards
+    #[serde(default)]
+    alarm: AlarmData
 
 }
 
@@ -36,9 +52,52 @@ pub fn load_level(world: &mut World, resources: &mut Resources, file: &str, dire
 
             resources.insert(WorldSize { width: l.width, height: l.height });
 
-            let mut codes = resources.get_mut::<Codes>().unwrap();           
-            collisionmap::load_collision(world, &mut codes, &l.collision.0, &l.collision.1);
-            eventmap::load_events(world, &mut codes, &l.event.0, &l.event.1);
+            //Subscope so that the borrow of the codes ends before the navigation graph and alarm are inserted
+            let alarm;
+
+            {
+
+                let mut codes = resources.get_mut::<Codes>().unwrap();           
+                let mut observers = resources.get_mut::<Observers>().unwrap();
+
+                collisionmap::load_collision(world, &mut codes, &l.collision.0, &l.collision.1);
+                eventmap::load_events(world, &mut codes, &l.event.0, &l.event.1);
+                if !l.guards.is_empty() && l.navigation.is_none()
+                {
+
+                    panic!("Level {} has guards but no navigation agent to build their paths for.", file);
+
+                }
+
+                guard::load_guards(world, &mut codes, &mut observers, l.guards);
+                surveillance::load_cameras(world, &mut codes, &mut observers, l.cameras);
+
+                //Every observer has registered its name by now, so terminals can be checked against them
+                let mut terminal_query = <&Terminal>::query();
+
+                if let Some(terminal) = terminal_query.iter(world).find(|terminal| { return observers.get(&terminal.observer).is_none(); })
+                {
+
+                    panic!("Terminal is linked to observer {}, which does not exist.", terminal.observer);
+
+                }
+
+                alarm = AlarmController::new(l.alarm, &mut codes);
+
+            }
+
+            resources.insert(alarm);
+            resources.get_mut::<Broadphase>().unwrap().mark_dirty();
+
+            let graph = match l.navigation
+            {
+
+                Some(agent) => collisionmap::build_navigation(world, &agent, &Guard::layers(), &resources.get::<Gravity>().unwrap(), resources.get::<Timestep>().unwrap().step),
+                None => NavGraph::default()
+
+            };
+
+            resources.insert(graph);
 
         }
         Err(e) => panic!("Unable to parse level RON file {} with error {}", file, e) 
@@ -1,4 +1,6 @@
 pub mod level;
 pub mod collisionmap;
 pub mod eventmap;
+pub mod guard;
 pub mod player;
+pub mod surveillance;
@@ -1,14 +1,21 @@
+use ron::de::from_reader;
+use serde::Deserialize;
+
 use legion::*;
 use legion::world::SubWorld;
 use legion::systems::{Builder, CommandBuffer};
 
+use std::fs::File;
+
+use engine::alarm::{Disabled, Hidden, LowProfile, Observers, Suspicious};
 use engine::codes::Codes;
 use engine::game::Timestep;
-use engine::physics::{DynamicBody, InteractsWithOneWay, ResetOneWayInteraction, RequestSizeChange, RequestSizeChangeSuccess, RequestSizeChangeFailure, Velocity};
-use engine::space::{FLOATING_POINT_ERROR, Rect};
+use engine::physics::{Anchor, Climbable, Contacts, DynamicBody, HasGravity, InteractsWithOneWay, NoWallJump, ResetOneWayInteraction, RequestSizeChange, RequestSizeChangeSuccess, RequestSizeChangeFailure, Layers, Material, Side, Solid, Submerged, Velocity, LAYER_ALL, LAYER_PLAYER};
+use engine::rope::{self, Rope, RopeGrip};
+use engine::space::{FLOATING_POINT_ERROR, Point, Rect};
 use engine::sprites::SpriteSheet;
 
-use super::eventmap::Trigger;
+use super::eventmap::{HideZone, Terminal, Trigger};
 
 const NUM_COMMANDS: usize = 6;
 
@@ -19,6 +26,28 @@ const PLAYER_DOWN: usize = 3;
 const PLAYER_JUMP: usize = 4;
 const PLAYER_INTERACT: usize = 5;
 
+//Visibility multipliers for the player's stance, and for standing still compared to moving at full speed
+const STANDING_VISIBILITY: f32 = 1.0;
+const CROUCHING_VISIBILITY: f32 = 0.5;
+const STILL_VISIBILITY: f32 = 0.5;
+
+//How much of the player has to be under water to start swimming, and how little to stop
+const SWIM_DEPTH: f32 = 0.6;
+const SURFACE_DEPTH: f32 = 0.3;
+
+//How long after a wall jump horizontal input is ignored, in milliseconds
+const WALL_JUMP_LOCK: i32 = 150;
+
+//How far below the player's feet a ladder can be grabbed by pressing down
+const LADDER_REACH: f32 = 1.0;
+
+//How far from the player's hands a rope can be grabbed, and how long after letting go horizontal input is ignored
+const ROPE_REACH: f32 = 12.0;
+const ROPE_RELEASE_LOCK: i32 = 200;
+
+//Crates heavier than the player are pushed more slowly than it walks
+const PLAYER_MASS: f32 = 1.0;
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum InputState
 {
@@ -132,7 +161,11 @@ enum PlayerState
 {
 
 	Normal,
-	Crouching
+	Crouching,
+	Climbing,
+	Swimming,
+	WallSliding,
+	Swinging
 
 }
 
@@ -142,22 +175,91 @@ pub struct Player
     run_speed: f32,
     jump_speed: f32,
 	crawl_speed: f32,
+	climb_speed: f32,
+	swim_speed: f32,
+	wall_slide_speed: f32,
+	wall_jump_speed: f32,
+	swing_force: f32,
+	acceleration: f32,
+	deceleration: f32,
+	air_control: f32,
+	jump_release: f32,
+	jumping: bool,
+	control_lock: i32,
 	state_time: i32,
 	state: PlayerState,
 	next: PlayerState
 
 }
 
+//Speeds are in pixels per second and acceleration in pixels per second squared, except for the jump speed which is the upward
+//velocity given per step. Air control scales acceleration while off the ground, and releasing jump early keeps only the
+//jump_release fraction of the upward velocity left. Swing force is how hard the player can push a rope they are holding
+#[derive(Deserialize)]
+pub struct PlayerData
+{
+
+	run_speed: f32,
+	jump_speed: f32,
+	crawl_speed: f32,
+	climb_speed: f32,
+	swim_speed: f32,
+	wall_slide_speed: f32,
+	wall_jump_speed: f32,
+	swing_force: f32,
+	acceleration: f32,
+	deceleration: f32,
+	air_control: f32,
+	jump_release: f32
+
+}
+
 impl Player
 {
 
-    pub fn new(r: f32, j: f32, c: f32) -> Player
+    pub fn new(data: &PlayerData) -> Player
     {
 
-        return Player { run_speed: r, jump_speed: j, crawl_speed: c, state_time: 0, state: PlayerState::Normal, next: PlayerState::Normal };
+        return Player
+		{
+
+			run_speed: data.run_speed,
+			jump_speed: data.jump_speed,
+			crawl_speed: data.crawl_speed,
+			climb_speed: data.climb_speed,
+			swim_speed: data.swim_speed,
+			wall_slide_speed: data.wall_slide_speed,
+			wall_jump_speed: data.wall_jump_speed,
+			swing_force: data.swing_force,
+			acceleration: data.acceleration,
+			deceleration: data.deceleration,
+			air_control: data.air_control,
+			jump_release: data.jump_release,
+			jumping: false,
+			control_lock: 0,
+			state_time: 0,
+			state: PlayerState::Normal,
+			next: PlayerState::Normal
+
+		};
 
     }
 
+	pub fn layers() -> Layers
+	{
+
+		return Layers::new(LAYER_PLAYER, LAYER_ALL);
+
+	}
+
+	//Hiding takes the player out of the way of crates and guards, so this is put back whenever they come out again
+	pub fn solid() -> Solid
+	{
+
+		return Solid { mass: PLAYER_MASS, pushable: true };
+
+	}
+
 	fn horizontal_speed(&self) -> f32
 	{
 
@@ -178,6 +280,20 @@ impl Player
 
 	}
 
+	fn stance_visibility(&self) -> f32
+	{
+
+		if self.state == PlayerState::Crouching
+		{
+
+			return CROUCHING_VISIBILITY;
+
+		}
+
+		return STANDING_VISIBILITY;
+
+	}
+
 	fn can_jump(&self) -> bool
 	{
 
@@ -208,6 +324,45 @@ impl Player
 
 	}
 
+	//Climbing needs no resize, so the state changes straight away. Gravity and one way platforms are left off while climbing,
+	//so that the player can climb through platforms at either end of a ladder
+	fn climb(&mut self, climbing: bool, cmd: &mut CommandBuffer, entity: Entity)
+	{
+
+		if climbing
+		{
+
+			self.change_state(PlayerState::Climbing);
+
+			cmd.remove_component::<HasGravity>(entity);
+			cmd.remove_component::<InteractsWithOneWay>(entity);
+
+		}
+		else
+		{
+
+			self.change_state(PlayerState::Normal);
+
+			cmd.add_component(entity, HasGravity {});
+			cmd.add_component(entity, InteractsWithOneWay {});
+
+		}
+
+		self.finish_state_change();
+
+	}
+
+	fn release_rope(&mut self, cmd: &mut CommandBuffer, entity: Entity)
+	{
+
+		self.change_state(PlayerState::Normal);
+		self.finish_state_change();
+
+		cmd.remove_component::<RopeGrip>(entity);
+		cmd.add_component(entity, InteractsWithOneWay {});
+
+	}
+
 }
 
 #[system(for_each)]
@@ -218,45 +373,466 @@ fn player_state_update(player: &mut Player, #[resource] step: &Timestep)
 
 }
 
+//Speeds up towards the chosen direction and slows down without input, both at rates set by the surface underfoot or by
+//air control. Jumps are cut short once the jump button is let go. Pressing against a wall in the air slides slowly down
+//it, and jumping off a wall kicks away from it
 #[system(for_each)]
-fn player_move(player: &mut Player, velocity: &mut Velocity, dynamic: &DynamicBody, #[resource] step: &Timestep, #[resource] input: &mut InputCommand)
+#[filter(!component::<Hidden>())]
+#[read_component(Material)]
+#[read_component(NoWallJump)]
+fn player_move(player: &mut Player, velocity: &mut Velocity, dynamic: &DynamicBody, contacts: &Contacts, world: &mut SubWorld, #[resource] step: &Timestep, #[resource] input: &mut InputCommand)
 {
 
-	if dynamic.top_collision == 0
+	if player.state == PlayerState::Climbing || player.state == PlayerState::Swimming || player.state == PlayerState::Swinging
 	{
 
-	    velocity.x = 0.0;
+		return;
 
-		let horizontal_speed = player.horizontal_speed();
+	}
 
-    	if input.down(PLAYER_LEFT)
-    	{
+	let seconds = step.step as f32 / 1000.0;
+	let grounded = dynamic.top_collision == 0;
 
-        	velocity.x = -horizontal_speed;
+	let material = contacts.ground()
+		.and_then(|ground| { return world.entry_ref(ground).ok(); })
+		.and_then(|entry| { return entry.get_component::<Material>().ok().copied(); })
+		.unwrap_or_default();
 
-    	}
+	let mut direction = 0.0;
 
-    	if input.down(PLAYER_RIGHT)
-    	{
+	if input.down(PLAYER_LEFT)
+	{
 
-        	velocity.x = horizontal_speed;
+		direction = -1.0;
 
-    	}
+	}
+
+	if input.down(PLAYER_RIGHT)
+	{
+
+		direction = 1.0;
+
+	}
+
+	//Directly after a wall jump the player cannot steer straight back into the wall
+	if player.control_lock > 0
+	{
+
+		player.control_lock -= step.step;
+
+	}
+	else
+	{
+
+		let target = direction * player.horizontal_speed() * material.speed * seconds;
+
+		//Turning round counts as slowing down
+		let speeding_up = direction != 0.0 && velocity.x * direction >= 0.0;
+		let rate = if speeding_up { player.acceleration } else { player.deceleration };
+		let control = if grounded { material.friction } else { player.air_control };
+
+		velocity.approach_x(target, rate * control * seconds * seconds);
+
+	}
+
+	let wall = if contacts.wall_left
+	{
+
+		contacts.on(Side::Left).map(|wall| { return (wall, -1.0); })
+
+	}
+	else if contacts.wall_right
+	{
+
+		contacts.on(Side::Right).map(|wall| { return (wall, 1.0); })
+
+	}
+	else
+	{
+
+		None
+
+	};
+
+	let wall = wall.filter(|_| { return !grounded; });
+
+	//Falling is measured along gravity so flipped players slide and jump the same way
+	let down = dynamic.down();
+	let sliding = wall.is_some_and(|(_, side)| { return side == direction; }) && velocity.y * down >= 0.0;
+
+	if player.state == PlayerState::Normal && sliding
+	{
+
+		player.change_state(PlayerState::WallSliding);
+		player.finish_state_change();
+
+	}
+	else if player.state == PlayerState::WallSliding && !sliding
+	{
+
+		player.change_state(PlayerState::Normal);
+		player.finish_state_change();
+
+	}
+
+	if player.state == PlayerState::WallSliding
+	{
 
-    	velocity.x *= step.step as f32 / 1000.0;
+		//Gravity is added after this, so leave room for however strongly it pulls on the player
+		velocity.y = (velocity.y * down).min(player.wall_slide_speed * seconds - dynamic.gravity.force) * down;
+
+	}
+
+	let kick = wall.filter(|(wall, _)|
+	{
+
+		return world.entry_ref(*wall).is_ok_and(|entry| { return entry.get_component::<NoWallJump>().is_err(); });
+
+	});
+
+	if let Some((_, side)) = kick.filter(|_| { return input.pressed(PLAYER_JUMP); })
+	{
+
+		velocity.x = -side * player.wall_jump_speed * seconds;
+		velocity.y = -player.jump_speed * down;
+
+		player.jumping = true;
+		player.control_lock = WALL_JUMP_LOCK;
+
+		if player.state == PlayerState::WallSliding
+		{
+
+			player.change_state(PlayerState::Normal);
+			player.finish_state_change();
+
+		}
+
+	}
+	else if grounded
+	{
+
+		player.jumping = false;
 
 		if input.pressed(PLAYER_JUMP) && input.up(PLAYER_DOWN) && player.can_jump()
     	{
 
-        	velocity.y = -player.jump_speed;
+        	velocity.y = -player.jump_speed * down;
+			player.jumping = true;
 
     	}
 
 	}
+	else if player.jumping && (velocity.y * down >= 0.0 || input.up(PLAYER_JUMP))
+	{
+
+		if velocity.y * down < 0.0
+		{
+
+			velocity.y *= player.jump_release;
+
+		}
+
+		player.jumping = false;
+
+	}
 
 }
 
+//Up grabs a ladder the player is in front of and down grabs one beneath their feet. Jumping lets go, as does climbing off
+//either end
 #[system(for_each)]
+#[read_component(Climbable)]
+#[filter(!component::<Hidden>())]
+fn player_ladder(player: &mut Player, dynamic: &mut DynamicBody, velocity: &mut Velocity, world: &mut SubWorld, cmd: &mut CommandBuffer, entity: &Entity, #[resource] step: &Timestep, #[resource] input: &mut InputCommand)
+{
+
+	let up = !input.up(PLAYER_UP);
+	let down = !input.up(PLAYER_DOWN);
+
+	let centre = dynamic.body.x + dynamic.body.width * 0.5;
+
+	let mut reach = dynamic.body;
+	reach.height += LADDER_REACH;
+
+	let mut ladder_query = <&Climbable>::query();
+	let ladder = ladder_query.iter(world)
+		.filter(|ladder| { return centre >= ladder.rect.x && centre <= ladder.rect.right(); })
+		.find(|ladder| { return Rect::intersects(&dynamic.body, &ladder.rect) || (down && Rect::intersects(&reach, &ladder.rect)); })
+		.map(|ladder| { return ladder.rect; });
+
+	if player.state == PlayerState::Normal
+	{
+
+		if let Some(ladder) = ladder.filter(|_| { return up || down; })
+		{
+
+			player.climb(true, cmd, *entity);
+
+			dynamic.body.x = ladder.x + (ladder.width - dynamic.body.width) * 0.5;
+			velocity.x = 0.0;
+			velocity.y = 0.0;
+
+		}
+
+		return;
+
+	}
+
+	if player.state != PlayerState::Climbing
+	{
+
+		return;
+
+	}
+
+	if input.pressed(PLAYER_JUMP)
+	{
+
+		player.climb(false, cmd, *entity);
+
+		velocity.y = -player.jump_speed * dynamic.down();
+		player.jumping = true;
+
+		return;
+
+	}
+
+	//Climbing down onto solid ground steps off the ladder
+	if ladder.is_none() || (down && !up && dynamic.top_collision == 0)
+	{
+
+		player.climb(false, cmd, *entity);
+
+		velocity.y = velocity.y.min(0.0);
+
+		return;
+
+	}
+
+	let direction = if down == up { 0.0 } else if down { 1.0 } else { -1.0 };
+
+	velocity.x = 0.0;
+	velocity.y = direction * player.climb_speed * step.step as f32 / 1000.0;
+
+}
+
+//Pressing up in the air grabs a rope within reach of the player's hands, carrying their momentum into the swing. Left and
+//right pump the swing, up and down climb the rope, and jumping lets go while keeping the swing's momentum. One way platforms
+//are ignored while swinging so that ropes can pass through them
+#[system]
+#[write_component(Player)]
+#[read_component(DynamicBody)]
+#[write_component(Velocity)]
+#[write_component(RopeGrip)]
+#[read_component(Rope)]
+fn player_rope(world: &mut SubWorld, cmd: &mut CommandBuffer, #[resource] step: &Timestep, #[resource] input: &mut InputCommand)
+{
+
+	let mut query = <(Entity, &mut Player, &DynamicBody, &mut Velocity, Option<&mut RopeGrip>)>::query().filter(!component::<Hidden>());
+	let (mut player_world, rope_world) = world.split_for_query(&query);
+
+	for (entity, player, dynamic, velocity, grip) in query.iter_mut(&mut player_world)
+	{
+
+		let hands = rope::hands(dynamic);
+
+		if player.state == PlayerState::Normal || player.state == PlayerState::WallSliding
+		{
+
+			if dynamic.top_collision == 0 || !input.pressed(PLAYER_UP)
+			{
+
+				continue;
+
+			}
+
+			let mut rope_query = <(Entity, &Rope)>::query();
+			let nearest = rope_query.iter(&rope_world)
+				.filter_map(|(entity, rope)| { return rope.nearest(hands, ROPE_REACH).map(|(index, distance)| { return (*entity, index, distance); }); })
+				.min_by(|a, b| { return a.2.total_cmp(&b.2); });
+
+			if let Some((rope, index, _)) = nearest
+			{
+
+				player.change_state(PlayerState::Swinging);
+				player.finish_state_change();
+
+				player.jumping = false;
+
+				cmd.add_component(*entity, RopeGrip::new(rope, index, Point { x: velocity.x, y: velocity.y }));
+				cmd.remove_component::<InteractsWithOneWay>(*entity);
+
+			}
+
+			continue;
+
+		}
+
+		if player.state != PlayerState::Swinging
+		{
+
+			continue;
+
+		}
+
+		//Ropes can disappear from under the player, who then just falls
+		let (grip, entry) = match grip.and_then(|grip| { return rope_world.entry_ref(grip.rope).ok().map(|entry| { return (grip, entry); }); })
+		{
+
+			Some(held) => held,
+			None =>
+			{
+
+				player.release_rope(cmd, *entity);
+
+				continue;
+
+			}
+
+		};
+
+		let rope = entry.get_component::<Rope>().unwrap();
+		let seconds = step.step as f32 / 1000.0;
+
+		let mut direction = 0.0;
+
+		if !input.up(PLAYER_LEFT)
+		{
+
+			direction = -1.0;
+
+		}
+
+		if !input.up(PLAYER_RIGHT)
+		{
+
+			direction = 1.0;
+
+		}
+
+		grip.push = Point { x: direction * player.swing_force * seconds * seconds, y: 0.0 };
+
+		if !input.up(PLAYER_UP)
+		{
+
+			grip.climb(rope, -player.climb_speed * seconds);
+
+		}
+		else if !input.up(PLAYER_DOWN)
+		{
+
+			grip.climb(rope, player.climb_speed * seconds);
+
+		}
+
+		if input.pressed(PLAYER_JUMP)
+		{
+
+			player.release_rope(cmd, *entity);
+
+			//The velocity is still the swing's, so the jump builds on whatever momentum the player had
+			let down = dynamic.down();
+
+			velocity.y = ((velocity.y * down).min(0.0) - player.jump_speed) * down;
+
+			player.jumping = true;
+			player.control_lock = ROPE_RELEASE_LOCK;
+
+		}
+
+	}
+
+}
+
+//Deep enough water switches the player to swimming, which moves in every direction while buoyancy holds them up. Jumping
+//with their head above the surface leaps back out
+#[system(for_each)]
+#[filter(!component::<Hidden>())]
+fn player_swim(player: &mut Player, dynamic: &DynamicBody, velocity: &mut Velocity, submerged: Option<&Submerged>, #[resource] step: &Timestep, #[resource] input: &mut InputCommand)
+{
+
+	let depth = submerged.map_or(0.0, |submerged| { return submerged.depth; });
+	let down = dynamic.down();
+
+	if player.state == PlayerState::Normal && depth >= SWIM_DEPTH && velocity.y * down >= 0.0
+	{
+
+		player.change_state(PlayerState::Swimming);
+		player.finish_state_change();
+
+		player.jumping = false;
+
+	}
+
+	if player.state != PlayerState::Swimming
+	{
+
+		return;
+
+	}
+
+	if depth < SURFACE_DEPTH
+	{
+
+		player.change_state(PlayerState::Normal);
+		player.finish_state_change();
+
+		return;
+
+	}
+
+	if depth < 1.0 && input.pressed(PLAYER_JUMP)
+	{
+
+		player.change_state(PlayerState::Normal);
+		player.finish_state_change();
+
+		velocity.y = -player.jump_speed * down;
+		player.jumping = true;
+
+		return;
+
+	}
+
+	let seconds = step.step as f32 / 1000.0;
+	let rate = player.acceleration * seconds * seconds;
+
+	let mut horizontal = 0.0;
+
+	if !input.up(PLAYER_LEFT)
+	{
+
+		horizontal = -1.0;
+
+	}
+
+	if !input.up(PLAYER_RIGHT)
+	{
+
+		horizontal = 1.0;
+
+	}
+
+	velocity.approach_x(horizontal * player.swim_speed * seconds, rate);
+
+	//Up always swims towards the surface
+	if !input.up(PLAYER_UP)
+	{
+
+		velocity.approach_y(-player.swim_speed * seconds * down, rate);
+
+	}
+	else if !input.up(PLAYER_DOWN)
+	{
+
+		velocity.approach_y(player.swim_speed * seconds * down, rate);
+
+	}
+
+}
+
+#[system(for_each)]
+#[filter(!component::<Hidden>())]
 fn player_oneway(_player: &Player, _interacts: &InteractsWithOneWay, cmd: &mut CommandBuffer, entity: &Entity, #[resource] input: &mut InputCommand)
 {
 
@@ -270,8 +846,83 @@ fn player_oneway(_player: &Player, _interacts: &InteractsWithOneWay, cmd: &mut C
 
 }
 
+//Interacting inside a hide zone hides the player, and interacting again leaves it
+#[system(for_each)]
+#[read_component(HideZone)]
+fn player_hide(_player: &Player, dynamic: &DynamicBody, velocity: &mut Velocity, hidden: Option<&Hidden>, world: &mut SubWorld, cmd: &mut CommandBuffer, entity: &Entity, #[resource] input: &mut InputCommand)
+{
+
+    match hidden
+    {
+
+        Some(_) =>
+        {
+
+            if input.pressed(PLAYER_INTERACT)
+            {
+
+                cmd.remove_component::<Hidden>(*entity);
+                cmd.add_component(*entity, Player::solid());
+
+            }
+
+        },
+        None =>
+        {
+
+            let mut zone_query = <&HideZone>::query();
+
+            let in_zone = zone_query.iter(world).any(|zone| { return Rect::intersects(&dynamic.body, &zone.rect); });
+
+            //Only poll the interact command inside a zone, so that triggers elsewhere still receive it
+            if in_zone && dynamic.top_collision == 0 && input.pressed(PLAYER_INTERACT)
+            {
+
+                velocity.x = 0.0;
+
+                //Hidden players are out of the way, so nothing can bump into them
+                cmd.add_component(*entity, Hidden::new());
+                cmd.remove_component::<Solid>(*entity);
+
+            }
+
+        }
+
+    }
+
+}
+
+#[system(for_each)]
+#[read_component(Terminal)]
+#[filter(!component::<Hidden>())]
+fn player_terminal(_player: &Player, dynamic: &DynamicBody, world: &mut SubWorld, cmd: &mut CommandBuffer, #[resource] input: &mut InputCommand, #[resource] observers: &Observers)
+{
+
+    let mut terminal_query = <&Terminal>::query();
+
+    for terminal in terminal_query.iter(world)
+    {
+
+        if Rect::intersects(&dynamic.body, &terminal.rect) && input.pressed(PLAYER_INTERACT)
+        {
+
+            //Links are checked when the level loads, so a missing observer has since been removed and there is nothing to disable
+            if let Some(observer) = observers.get(&terminal.observer)
+            {
+
+                cmd.add_component(observer, Disabled::new(terminal.duration));
+
+            }
+
+        }
+
+    }
+
+}
+
 #[system(for_each)]
 #[write_component(Trigger)]
+#[filter(!component::<Hidden>())]
 fn player_trigger(_player: &Player, dynamic: &DynamicBody, world: &mut SubWorld, cmd: &mut CommandBuffer,#[resource] input: &mut InputCommand, #[resource] codes: &mut Codes)
 {
 
@@ -300,9 +951,13 @@ fn player_trigger(_player: &Player, dynamic: &DynamicBody, world: &mut SubWorld,
 }
 
 #[system(for_each)]
+#[filter(!component::<Hidden>())]
 fn player_state(player: &mut Player, dynamic: &DynamicBody, cmd: &mut CommandBuffer, entity: &Entity, #[resource] input: &mut InputCommand)
 {
 
+	//Crouch towards whatever the player is standing on
+	let floor = if dynamic.flipped() { Anchor::Top } else { Anchor::Bottom };
+
 	if player.state == PlayerState::Normal
 	{
 
@@ -311,7 +966,7 @@ fn player_state(player: &mut Player, dynamic: &DynamicBody, cmd: &mut CommandBuf
 
 			player.change_state(PlayerState::Crouching);
 
-			cmd.add_component(*entity, RequestSizeChange { width: dynamic.body.width, height: dynamic.body.height * 0.5 });
+			cmd.add_component(*entity, RequestSizeChange::new(dynamic.body.width, dynamic.body.height * 0.5).with_anchor(floor));
 
 		}
 
@@ -325,7 +980,7 @@ fn player_state(player: &mut Player, dynamic: &DynamicBody, cmd: &mut CommandBuf
 
 			player.change_state(PlayerState::Normal);
 
-			cmd.add_component(*entity, RequestSizeChange { width: dynamic.body.width, height: dynamic.body.height * 2.0 } );
+			cmd.add_component(*entity, RequestSizeChange::new(dynamic.body.width, dynamic.body.height * 2.0).with_anchor(floor));
 
 		}
 
@@ -349,10 +1004,35 @@ fn player_resize_success(player: &mut Player, _success: &RequestSizeChangeSucces
 
 	player.finish_state_change();
 
+	if player.state == PlayerState::Crouching
+	{
+
+		cmd.add_component(*entity, LowProfile {});
+
+	}
+	else
+	{
+
+		cmd.remove_component::<LowProfile>(*entity);
+
+	}
+
 	cmd.remove_component::<RequestSizeChangeFailure>(*entity);
 
 }
 
+//Moving faster than a crawl makes the player easier to spot, as does standing up
+#[system(for_each)]
+fn player_visibility(player: &Player, velocity: &Velocity, suspicious: &mut Suspicious, #[resource] step: &Timestep)
+{
+
+	let full_speed = player.run_speed * step.step as f32 / 1000.0;
+	let movement = (velocity.x.abs() / full_speed).min(1.0);
+
+	suspicious.visibility = player.stance_visibility() * (STILL_VISIBILITY + (1.0 - STILL_VISIBILITY) * movement);
+
+}
+
 #[system(for_each)]
 fn player_animation(_player: &Player, velocity: &Velocity, dynamic: &DynamicBody, sprite: &mut SpriteSheet)
 {
@@ -394,16 +1074,38 @@ fn player_animation(_player: &Player, velocity: &Velocity, dynamic: &DynamicBody
 
 }
 
+pub fn load_player(file: &str, directory: &str) -> Player
+{
+
+    let f = File::open(&format!("{}{}.ron", directory, file)).expect(&format!("Unable to open player file {}", file));
+    let parse: Result<PlayerData, _> = from_reader(f);
+
+    match parse
+    {
+
+        Ok(data) => return Player::new(&data),
+        Err(e) => panic!("Unable to parse player RON file {} with error {}", file, e)
+
+    }
+
+}
+
 pub fn schedule_early_systems(schedule: &mut Builder)
 {
 
 	schedule.add_system(player_resize_success_system());
 	schedule.add_system(player_resize_failure_system());
 	schedule.add_system(player_state_update_system());
+	schedule.add_system(player_ladder_system());
+	schedule.add_system(player_rope_system());
+	schedule.add_system(player_swim_system());
 	schedule.add_system(player_move_system());
 	schedule.add_system(player_oneway_system());
+	schedule.add_system(player_hide_system());
+	schedule.add_system(player_terminal_system());
     schedule.add_system(player_trigger_system());
     schedule.add_system(player_state_system());
+	schedule.add_system(player_visibility_system());
 		
 }
 
@@ -413,3 +1115,282 @@ pub fn schedule_animation_systems(schedule: &mut Builder)
    schedule.add_system(player_animation_system());
 
 }
+
+#[cfg(test)]
+mod tests
+{
+
+	use super::*;
+
+	use engine::physics::{BodyGravity, Contact};
+
+	fn player() -> Player
+	{
+
+		let data = PlayerData
+		{
+
+			run_speed: 200.0,
+			jump_speed: 8.0,
+			crawl_speed: 50.0,
+			climb_speed: 100.0,
+			swim_speed: 120.0,
+			wall_slide_speed: 80.0,
+			wall_jump_speed: 250.0,
+			swing_force: 400.0,
+			acceleration: 1500.0,
+			deceleration: 2000.0,
+			air_control: 0.4,
+			jump_release: 0.5
+
+		};
+
+		return Player::new(&data);
+
+	}
+
+	fn resources() -> Resources
+	{
+
+		let mut resources = Resources::default();
+
+		resources.insert(Timestep { step: 16 });
+		resources.insert(InputCommand::new());
+
+		return resources;
+
+	}
+
+	fn touching(other: Entity, side: Side) -> Contacts
+	{
+
+		let mut contacts = Contacts::new();
+
+		let normal = match side
+		{
+
+			Side::Bottom => Point { x: 0.0, y: -1.0 },
+			Side::Top => Point { x: 0.0, y: 1.0 },
+			Side::Left => Point { x: 1.0, y: 0.0 },
+			Side::Right => Point { x: -1.0, y: 0.0 }
+
+		};
+
+		contacts.grounded = side == Side::Bottom;
+		contacts.wall_left = side == Side::Left;
+		contacts.wall_right = side == Side::Right;
+		contacts.touching.push(Contact { other, side, normal });
+
+		return contacts;
+
+	}
+
+	#[test]
+	fn surface_friction()
+	{
+
+		let mut world = World::default();
+		let mut resources = resources();
+		let mut schedule = Schedule::builder().add_system(player_move_system()).build();
+
+		let floor = world.push((Material::default(),));
+		let ice = world.push((Material { friction: 0.1, speed: 1.0 },));
+		let mud = world.push((Material { friction: 1.0, speed: 0.5 },));
+
+		let players: Vec<Entity> = [floor, ice, mud].iter().map(|surface|
+		{
+
+			let mut grounded = DynamicBody::new(0.0, 0.0, 15.0, 30.0);
+			grounded.top_collision = 0;
+
+			return world.push((player(), Velocity::new(0.0, 0.0), grounded, touching(*surface, Side::Bottom)));
+
+		}).collect();
+
+		resources.get_mut::<InputCommand>().unwrap().commands[PLAYER_RIGHT] = InputState::Down;
+
+		schedule.execute(&mut world, &mut resources);
+
+		let speeds = |world: &World| { return players.iter().map(|p| { return world.entry_ref(*p).unwrap().get_component::<Velocity>().unwrap().x; }).collect::<Vec<f32>>(); };
+
+		//Friction scales how quickly the player gets up to speed
+		let first = speeds(&world);
+
+		assert!((first[0] - 0.384).abs() < FLOATING_POINT_ERROR);
+		assert!((first[1] - 0.0384).abs() < FLOATING_POINT_ERROR);
+		assert!((first[2] - 0.384).abs() < FLOATING_POINT_ERROR);
+
+		//Speed scales how fast they end up going
+		for _ in 0..100
+		{
+
+			schedule.execute(&mut world, &mut resources);
+
+		}
+
+		let top = speeds(&world);
+
+		assert!((top[0] - 3.2).abs() < FLOATING_POINT_ERROR);
+		assert!((top[1] - 3.2).abs() < FLOATING_POINT_ERROR);
+		assert!((top[2] - 1.6).abs() < FLOATING_POINT_ERROR);
+
+	}
+
+	#[test]
+	fn ladder_grab()
+	{
+
+		let mut world = World::default();
+		let mut resources = resources();
+		let mut schedule = Schedule::builder().add_system(player_ladder_system()).build();
+
+		world.push((Climbable { rect: Rect { x: 100.0, y: 0.0, width: 20.0, height: 100.0 } },));
+
+		let climber = world.push((player(), DynamicBody::new(98.0, 50.0, 15.0, 30.0), Velocity::new(3.0, 2.0), HasGravity {}, InteractsWithOneWay {}));
+
+		let state = |world: &World| { return world.entry_ref(climber).unwrap().get_component::<Player>().unwrap().state; };
+
+		//Nothing happens without pressing up or down
+		schedule.execute(&mut world, &mut resources);
+
+		assert!(state(&world) == PlayerState::Normal);
+
+		//Grabbing centres the player on the ladder, stops them and takes away gravity
+		resources.get_mut::<InputCommand>().unwrap().commands[PLAYER_UP] = InputState::Down;
+		schedule.execute(&mut world, &mut resources);
+
+		let entry = world.entry_ref(climber).unwrap();
+
+		assert!(state(&world) == PlayerState::Climbing);
+		assert_eq!(entry.get_component::<DynamicBody>().unwrap().body.x, 102.5);
+		assert_eq!(entry.get_component::<Velocity>().unwrap().y, 0.0);
+		assert!(entry.get_component::<HasGravity>().is_err());
+		assert!(entry.get_component::<InteractsWithOneWay>().is_err());
+
+		//Jumping lets go and gives gravity back
+		resources.get_mut::<InputCommand>().unwrap().commands[PLAYER_UP] = InputState::Up;
+		resources.get_mut::<InputCommand>().unwrap().commands[PLAYER_JUMP] = InputState::Pressed;
+		schedule.execute(&mut world, &mut resources);
+
+		let entry = world.entry_ref(climber).unwrap();
+
+		assert!(state(&world) == PlayerState::Normal);
+		assert_eq!(entry.get_component::<Velocity>().unwrap().y, -8.0);
+		assert!(entry.get_component::<HasGravity>().is_ok());
+
+		//Climbing off the top of the ladder lets go as well
+		resources.get_mut::<InputCommand>().unwrap().commands[PLAYER_UP] = InputState::Down;
+		schedule.execute(&mut world, &mut resources);
+
+		assert!(state(&world) == PlayerState::Climbing);
+
+		world.entry(climber).unwrap().get_component_mut::<DynamicBody>().unwrap().body.y = -40.0;
+		schedule.execute(&mut world, &mut resources);
+
+		assert!(state(&world) == PlayerState::Normal);
+
+	}
+
+	#[test]
+	fn wall_slide()
+	{
+
+		//Each wall gets its own world, so that one player taking the jump press cannot hide whether the other would have
+		let slide = |wall_jump: bool|
+		{
+
+			let mut world = World::default();
+			let mut resources = resources();
+			let mut schedule = Schedule::builder().add_system(player_move_system()).build();
+
+			let wall = world.push((Material::default(),));
+
+			if !wall_jump
+			{
+
+				world.entry(wall).unwrap().add_component(NoWallJump {});
+
+			}
+
+			let mut body = DynamicBody::new(0.0, 0.0, 15.0, 30.0);
+			body.gravity = BodyGravity { force: 0.32, max: 16.0, down: 1.0 };
+
+			let entity = world.push((player(), Velocity::new(0.0, 10.0), body, touching(wall, Side::Right)));
+
+			//Pushing into a wall while falling slides down it slowly, leaving room for gravity to be added afterwards
+			resources.get_mut::<InputCommand>().unwrap().commands[PLAYER_RIGHT] = InputState::Down;
+			schedule.execute(&mut world, &mut resources);
+
+			let entry = world.entry_ref(entity).unwrap();
+
+			assert!(entry.get_component::<Player>().unwrap().state == PlayerState::WallSliding);
+			assert!((entry.get_component::<Velocity>().unwrap().y - (80.0 * 0.016 - 0.32)).abs() < FLOATING_POINT_ERROR);
+
+			resources.get_mut::<InputCommand>().unwrap().commands[PLAYER_JUMP] = InputState::Pressed;
+			schedule.execute(&mut world, &mut resources);
+
+			let entry = world.entry_ref(entity).unwrap();
+			let velocity = entry.get_component::<Velocity>().unwrap();
+
+			return (entry.get_component::<Player>().unwrap().state, velocity.x, velocity.y);
+
+		};
+
+		//Jumping kicks off the wall
+		let (state, x, y) = slide(true);
+
+		assert!(state == PlayerState::Normal);
+		assert!((x + 4.0).abs() < FLOATING_POINT_ERROR);
+		assert_eq!(y, -8.0);
+
+		//Unless it cannot be wall jumped from, in which case the jump is refused and the player keeps sliding
+		let (state, x, y) = slide(false);
+
+		assert!(state == PlayerState::WallSliding);
+		assert!(x >= 0.0);
+		assert!(y > 0.0);
+
+	}
+
+	#[test]
+	fn flipped_swim()
+	{
+
+		//Enters the water sinking the way gravity pulls, then jumps out of the surface. Returns the vertical speed of the jump
+		let swim = |down: f32|
+		{
+
+			let mut world = World::default();
+			let mut resources = resources();
+			let mut schedule = Schedule::builder().add_system(player_swim_system()).build();
+
+			let fluid = world.push((0,));
+
+			let mut body = DynamicBody::new(0.0, 0.0, 15.0, 30.0);
+			body.gravity = BodyGravity { force: 0.32, max: 16.0, down };
+
+			let swimmer = world.push((player(), body, Velocity::new(0.0, down), Submerged { fluid, depth: 0.8, clarity: 1.0 }));
+
+			schedule.execute(&mut world, &mut resources);
+
+			let entry = world.entry_ref(swimmer).unwrap();
+
+			assert!(entry.get_component::<Player>().unwrap().state == PlayerState::Swimming);
+
+			resources.get_mut::<InputCommand>().unwrap().commands[PLAYER_JUMP] = InputState::Pressed;
+			schedule.execute(&mut world, &mut resources);
+
+			let entry = world.entry_ref(swimmer).unwrap();
+
+			assert!(entry.get_component::<Player>().unwrap().state == PlayerState::Normal);
+
+			return entry.get_component::<Velocity>().unwrap().y;
+
+		};
+
+		assert_eq!(swim(1.0), -8.0);
+		assert_eq!(swim(-1.0), 8.0);
+
+	}
+
+}
@@ -0,0 +1,50 @@
+use serde::Deserialize;
+
+use legion::*;
+
+use engine::alarm::{Cone, Observer, ObserverData, Observers, Radio};
+use engine::codes::Codes;
+use engine::space::Point;
+
+#[derive(Deserialize)]
+pub struct CameraData
+{
+
+	location: Point,
+	observer: ObserverData
+
+}
+
+pub fn load_cameras(world: &mut World, codes: &mut Codes, observers: &mut Observers, cameras: Vec<CameraData>)
+{
+
+	for data in cameras
+	{
+
+		let observer = Observer::new(data.location, data.observer.offset, data.observer.upper, data.observer.lower, codes.get_code(&data.observer.code), data.observer.reaction);
+
+		let entity = world.push(
+		(
+
+			observer,
+			Cone { field: Vec::new() }
+
+		));
+
+		if let Some(radio) = &data.observer.radio
+		{
+
+			world.entry(entity).unwrap().add_component(Radio::new(radio, codes));
+
+		}
+
+		if let Some(name) = &data.observer.name
+		{
+
+			observers.insert(name, entity);
+
+		}
+
+	}
+
+}